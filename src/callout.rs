@@ -0,0 +1,98 @@
+use bevy::prelude::*;
+use bevy::text::Text2dBounds;
+use tetrominos_core::game::Spin;
+
+use crate::game_area::*;
+
+/// Short text such as "T-SPIN DOUBLE" flashed next to the board for a moment.
+pub struct CalloutEvent(pub String);
+
+#[derive(Component)]
+pub struct Callout {
+    timer: Timer,
+}
+
+pub fn t_spin_callout(spin: Spin, lines: i32) -> CalloutEvent {
+    let kind = match spin {
+        Spin::Mini => "T-SPIN MINI",
+        _ => "T-SPIN",
+    };
+    let lines = match lines {
+        1 => " SINGLE",
+        2 => " DOUBLE",
+        3 => " TRIPLE",
+        _ => "",
+    };
+    CalloutEvent(format!("{}{}", kind, lines))
+}
+
+pub fn back_to_back_callout(chain: u32) -> CalloutEvent {
+    if chain > 1 {
+        CalloutEvent(format!("BACK-TO-BACK x{}", chain))
+    } else {
+        CalloutEvent(String::from("BACK-TO-BACK"))
+    }
+}
+
+/// Replaces the callout on screen with the ones sent this frame, one per line.
+pub fn show_callouts(
+    mut commands: Commands,
+    mut callout_reader: EventReader<CalloutEvent>,
+    callout_query: Query<(&Callout, Entity)>,
+    asset_server: Res<AssetServer>,
+) {
+    let lines: Vec<&str> = callout_reader
+        .iter()
+        .map(|callout| callout.0.as_str())
+        .collect();
+    if lines.is_empty() {
+        return;
+    }
+
+    callout_query.for_each(|(_, entity)| {
+        commands.entity(entity).despawn();
+    });
+
+    let font = asset_server.load("fonts/FiraSans-Bold.ttf");
+    let text_style = TextStyle {
+        font,
+        font_size: CALLOUT_FONT_SIZE,
+        color: Color::WHITE,
+    };
+    let height = CALLOUT_FONT_SIZE * CALLOUT_LINES;
+    commands.spawn((
+        Callout {
+            timer: Timer::from_seconds(CALLOUT_SECONDS, TimerMode::Once),
+        },
+        Text2dBundle {
+            text: Text::from_section(lines.join("\n"), text_style)
+                .with_alignment(TextAlignment::CENTER),
+            text_2d_bounds: Text2dBounds {
+                size: Vec2::new(CALLOUT_WIDTH, height),
+            },
+            transform: Transform {
+                translation: calculate_translation(
+                    CALLOUT_CORNER.x,
+                    CALLOUT_CORNER.y,
+                    2.,
+                    CALLOUT_WIDTH,
+                    height,
+                ),
+                ..default()
+            },
+            ..default()
+        },
+    ));
+}
+
+pub fn expire_callouts(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut callout_query: Query<(&mut Callout, Entity)>,
+) {
+    for (mut callout, entity) in callout_query.iter_mut() {
+        if callout.timer.tick(time.delta()).finished() {
+            commands.entity(entity).despawn();
+        }
+    }
+}
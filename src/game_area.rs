@@ -1,33 +1,76 @@
 use bevy::math::{Vec2, Vec3};
 use bevy::prelude::Transform;
+use tetrominos_core::board::{BOARD_HEIGHT, BOARD_WIDTH};
 
-pub const HORIZONTAL_TILES: u32 = 10;
-pub const VERTICAL_TILES: u32 = 20;
+pub const HORIZONTAL_TILES: u32 = BOARD_WIDTH as u32;
+pub const VERTICAL_TILES: u32 = BOARD_HEIGHT as u32;
 pub const TILE_SIZE: f32 = 30.0;
 pub const GAME_AREA: Vec2 = Vec2::new(
     HORIZONTAL_TILES as f32 * TILE_SIZE,
     VERTICAL_TILES as f32 * TILE_SIZE,
 );
-pub const PREVIEW_TILES: i32 = 4;
-pub const PREVIEW_AREA: Vec2 = Vec2::new(
-    PREVIEW_TILES as f32 * TILE_SIZE + 2. * MARGIN,
-    PREVIEW_TILES as f32 * TILE_SIZE + 2. * MARGIN,
+/// Tiles across the box a piece of the next queue or the hold is drawn in.
+pub const SLOT_TILES: i32 = 4;
+pub const SLOT_AREA: Vec2 = Vec2::new(
+    SLOT_TILES as f32 * TILE_SIZE + 2. * MARGIN,
+    SLOT_TILES as f32 * TILE_SIZE + 2. * MARGIN,
 );
-pub const PREVIEW_CORNER: Vec2 = Vec2::new(GAME_AREA.x + TILE_SIZE, TILE_SIZE);
+pub const QUEUE_CORNER: Vec2 = Vec2::new(GAME_AREA.x + TILE_SIZE, TILE_SIZE);
+pub const HOLD_CORNER: Vec2 = Vec2::new(QUEUE_CORNER.x + SLOT_AREA.x + TILE_SIZE, TILE_SIZE);
+pub const STATS_CORNER: Vec2 = Vec2::new(HOLD_CORNER.x + SLOT_AREA.x + TILE_SIZE, TILE_SIZE);
+pub const STATS_WIDTH: f32 = 160.0;
+pub const STATS_FONT_SIZE: f32 = 20.0;
+pub const STATS_LINE_HEIGHT: f32 = 24.0;
+pub const STATS_SUMMARY_LINES: f32 = 6.0;
+pub const STATS_TILE_SIZE: f32 = 8.0;
+/// Room left of the piece counts for the widest piece icon.
+pub const STATS_ICON_WIDTH: f32 = 5. * STATS_TILE_SIZE + MARGIN;
+pub const SIDE_PANEL_WIDTH: f32 = 2. * SLOT_AREA.x + 2. * TILE_SIZE + STATS_WIDTH;
+pub const SMALL_TILE_SIZE: f32 = TILE_SIZE / 2.;
+/// Height every piece after the first adds to the next queue.
+pub const SMALL_SLOT_HEIGHT: f32 = SLOT_TILES as f32 * SMALL_TILE_SIZE + MARGIN;
 pub const MARGIN: f32 = 10.0;
+pub const GHOST_ALPHA: f32 = 0.3;
+/// Brightness of garbage cells relative to the grey tile image.
+pub const GARBAGE_SHADE: f32 = 0.45;
+pub const OVERLAY_ALPHA: f32 = 0.7;
+pub const OVERLAY_FONT_SIZE: f32 = 30.0;
+pub const BINDINGS_FONT_SIZE: f32 = 20.0;
 
-pub const LEFT_RIGHT_MOVE_SLEEP: u64 = 100;
-pub const DOWN_MOVE_SLEEP: u64 = 100;
-pub const INITIAL_DESCEND_SLEEP: u64 = 1000;
-
-pub const SCORE_BOARD_WIDTH: f32 = 200.0;
+pub const SCORE_BOARD_WIDTH: f32 = SLOT_AREA.x;
 pub const SCORE_BOARD_HEIGHT: f32 = 40.0;
+pub const SCORE_CORNER: Vec2 = Vec2::new(HOLD_CORNER.x, HOLD_CORNER.y + SLOT_AREA.y + TILE_SIZE);
+
+pub const MODE_CORNER: Vec2 =
+    Vec2::new(SCORE_CORNER.x, SCORE_CORNER.y + SCORE_BOARD_HEIGHT + MARGIN);
+pub const MODE_AREA: Vec2 = Vec2::new(SLOT_AREA.x, 2. * MODE_FONT_SIZE + MARGIN);
+pub const MODE_FONT_SIZE: f32 = 24.0;
+
+pub const CALLOUT_CORNER: Vec2 = Vec2::new(MODE_CORNER.x, MODE_CORNER.y + MODE_AREA.y + TILE_SIZE);
+pub const CALLOUT_WIDTH: f32 = SLOT_AREA.x;
+pub const CALLOUT_FONT_SIZE: f32 = 20.0;
+pub const CALLOUT_SECONDS: f32 = 1.5;
+pub const CALLOUT_LINES: f32 = 5.0;
 
 pub const BOUNDS: Vec2 = Vec2::new(
-    MARGIN + GAME_AREA.x + TILE_SIZE + SCORE_BOARD_WIDTH + MARGIN,
+    MARGIN + GAME_AREA.x + TILE_SIZE + SIDE_PANEL_WIDTH + MARGIN,
     GAME_AREA.y + 2. * MARGIN,
 );
 
+/// Width of the incoming garbage meter left of each board in versus.
+pub const METER_WIDTH: f32 = 15.0;
+/// Pieces of the next queue shown for each player in versus.
+pub const VERSUS_QUEUE_LENGTH: usize = 3;
+/// Width of everything drawn for one player in versus: meter, board and side panel.
+pub const VERSUS_FIELD_WIDTH: f32 = METER_WIDTH + MARGIN + GAME_AREA.x + TILE_SIZE + SLOT_AREA.x;
+pub const VERSUS_FIELD_GAP: f32 = 2. * TILE_SIZE;
+pub const VERSUS_BOUNDS: Vec2 = Vec2::new(
+    MARGIN + 2. * VERSUS_FIELD_WIDTH + VERSUS_FIELD_GAP + MARGIN,
+    BOUNDS.y,
+);
+/// Corner of the screens drawn over the game in versus, centered between both fields.
+pub const VERSUS_OVERLAY_CORNER: Vec2 = Vec2::new((BOUNDS.x - GAME_AREA.x) / 2. - MARGIN, 0.);
+
 pub fn calculate_translation(x: f32, y: f32, z: f32, width: f32, height: f32) -> Vec3 {
     Vec3::new(
         (BOUNDS.x - width) / -2. + MARGIN + x,
@@ -37,35 +80,97 @@ pub fn calculate_translation(x: f32, y: f32, z: f32, width: f32, height: f32) ->
 }
 
 pub fn calculate_transform(x: f32, y: f32, z: f32, width: f32, height: f32) -> Transform {
-    return Transform {
+    Transform {
         translation: calculate_translation(x, y, z, width, height),
         scale: Vec3::new(width, height, 0.),
         ..Default::default()
-    };
+    }
 }
 
 pub fn tile_transform(coords: (i32, i32)) -> Transform {
-    return Transform {
+    board_tile_transform(Vec2::ZERO, coords)
+}
+
+/// Transform of a tile on a board drawn with its top left corner at `corner`.
+pub fn board_tile_transform(corner: Vec2, coords: (i32, i32)) -> Transform {
+    Transform {
         translation: calculate_translation(
-            coords.0 as f32 * TILE_SIZE,
-            coords.1 as f32 * TILE_SIZE,
+            corner.x + coords.0 as f32 * TILE_SIZE,
+            corner.y + coords.1 as f32 * TILE_SIZE,
             1.,
             TILE_SIZE,
             TILE_SIZE,
         ),
         ..Default::default()
-    };
+    }
 }
 
-pub fn preview_tile_translation(coords: (i32, i32), x_adjust: f32, y_adjust: f32) -> Transform {
-    return Transform {
+pub fn panel_tile_translation(
+    corner: Vec2,
+    tile_size: f32,
+    coords: (i32, i32),
+    x_adjust: f32,
+    y_adjust: f32,
+) -> Transform {
+    Transform {
         translation: calculate_translation(
-            MARGIN + PREVIEW_CORNER.x + (coords.0 as f32 * TILE_SIZE) + x_adjust,
-            MARGIN + PREVIEW_CORNER.y + (coords.1 as f32 * TILE_SIZE) + y_adjust,
+            MARGIN + corner.x + (coords.0 as f32 * tile_size) + x_adjust,
+            MARGIN + corner.y + (coords.1 as f32 * tile_size) + y_adjust,
             1.,
-            TILE_SIZE,
-            TILE_SIZE,
+            tile_size,
+            tile_size,
         ),
+        scale: Vec3::new(tile_size / TILE_SIZE, tile_size / TILE_SIZE, 1.),
         ..Default::default()
-    };
+    }
+}
+
+/// Size of the next queue panel showing `length` pieces, the first one at full size and the
+/// rest at half size below it.
+pub fn queue_area(length: usize) -> Vec2 {
+    Vec2::new(
+        SLOT_AREA.x,
+        SLOT_AREA.y + (length.max(1) - 1) as f32 * SMALL_SLOT_HEIGHT,
+    )
+}
+
+/// Corner of the box the `index`th piece of the next queue starting at `corner` is centered
+/// in, and the size of its tiles.
+pub fn queue_slot(corner: Vec2, index: usize) -> (Vec2, f32) {
+    if index == 0 {
+        return (corner, TILE_SIZE);
+    }
+    let small_box = SLOT_TILES as f32 * SMALL_TILE_SIZE + 2. * MARGIN;
+    (
+        Vec2::new(
+            corner.x + (SLOT_AREA.x - small_box) / 2.,
+            corner.y + SLOT_AREA.y - MARGIN + (index - 1) as f32 * SMALL_SLOT_HEIGHT,
+        ),
+        SMALL_TILE_SIZE,
+    )
+}
+
+/// Top left corner of the meter of `player` in versus, the leftmost part of their field.
+pub fn versus_field_corner(player: usize) -> Vec2 {
+    Vec2::new(
+        (BOUNDS.x - VERSUS_BOUNDS.x) / 2. + player as f32 * (VERSUS_FIELD_WIDTH + VERSUS_FIELD_GAP),
+        0.,
+    )
+}
+
+pub fn versus_board_corner(player: usize) -> Vec2 {
+    versus_field_corner(player) + Vec2::new(METER_WIDTH + MARGIN, 0.)
+}
+
+pub fn versus_queue_corner(player: usize) -> Vec2 {
+    versus_board_corner(player) + Vec2::new(GAME_AREA.x + TILE_SIZE, TILE_SIZE)
+}
+
+pub fn versus_hold_corner(player: usize) -> Vec2 {
+    versus_queue_corner(player) + Vec2::new(0., queue_area(VERSUS_QUEUE_LENGTH).y + TILE_SIZE)
+}
+
+/// Corner of the score and garbage sent of `player` in versus, under their hold.
+pub fn versus_score_corner(player: usize) -> Vec2 {
+    versus_hold_corner(player) + Vec2::new(0., SLOT_AREA.y + TILE_SIZE)
 }
@@ -0,0 +1,578 @@
+use std::collections::{BTreeMap, HashSet};
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+use tetrominos_core::game::Inputs;
+
+use crate::settings::{load_config, save_config, Settings};
+
+const BINDINGS_FILE: &str = "bindings.ron";
+const VERSUS_BINDINGS_FILE: &str = "versus_bindings.ron";
+
+/// Everything a player can ask of the game, independent of the key or button it is bound to.
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Debug, Serialize, Deserialize)]
+pub enum Action {
+    MoveLeft,
+    MoveRight,
+    SoftDrop,
+    HardDrop,
+    RotateCW,
+    RotateCCW,
+    Rotate180,
+    Hold,
+    Pause,
+}
+
+impl Action {
+    pub const ALL: [Action; 9] = [
+        Action::MoveLeft,
+        Action::MoveRight,
+        Action::SoftDrop,
+        Action::HardDrop,
+        Action::RotateCW,
+        Action::RotateCCW,
+        Action::Rotate180,
+        Action::Hold,
+        Action::Pause,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Action::MoveLeft => "Move left",
+            Action::MoveRight => "Move right",
+            Action::SoftDrop => "Soft drop",
+            Action::HardDrop => "Hard drop",
+            Action::RotateCW => "Rotate CW",
+            Action::RotateCCW => "Rotate CCW",
+            Action::Rotate180 => "Rotate 180",
+            Action::Hold => "Hold",
+            Action::Pause => "Pause",
+        }
+    }
+}
+
+/// Keys and gamepad buttons bound to each action, kept in `bindings.ron` in the config
+/// directory.
+#[derive(Resource, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Bindings {
+    keys: BTreeMap<Action, Vec<KeyCode>>,
+    buttons: BTreeMap<Action, Vec<GamepadButtonType>>,
+}
+
+impl Default for Bindings {
+    fn default() -> Self {
+        Bindings {
+            keys: BTreeMap::from([
+                (Action::MoveLeft, vec![KeyCode::Left]),
+                (Action::MoveRight, vec![KeyCode::Right]),
+                (Action::SoftDrop, vec![KeyCode::Down]),
+                (Action::HardDrop, vec![KeyCode::Space]),
+                (Action::RotateCW, vec![KeyCode::Up, KeyCode::X]),
+                (Action::RotateCCW, vec![KeyCode::Z]),
+                (Action::Rotate180, vec![KeyCode::A]),
+                (Action::Hold, vec![KeyCode::C, KeyCode::LShift]),
+                (Action::Pause, vec![KeyCode::P]),
+            ]),
+            buttons: BTreeMap::from([
+                (Action::MoveLeft, vec![GamepadButtonType::DPadLeft]),
+                (Action::MoveRight, vec![GamepadButtonType::DPadRight]),
+                (Action::SoftDrop, vec![GamepadButtonType::DPadDown]),
+                (Action::HardDrop, vec![GamepadButtonType::DPadUp]),
+                (Action::RotateCW, vec![GamepadButtonType::South]),
+                (Action::RotateCCW, vec![GamepadButtonType::East]),
+                (Action::Rotate180, vec![GamepadButtonType::North]),
+                (
+                    Action::Hold,
+                    vec![
+                        GamepadButtonType::LeftTrigger,
+                        GamepadButtonType::RightTrigger,
+                    ],
+                ),
+                (Action::Pause, vec![GamepadButtonType::Start]),
+            ]),
+        }
+    }
+}
+
+impl Bindings {
+    /// Default keys of a player in versus, the first player on the left of the keyboard and
+    /// the second one on the right, each with the usual gamepad buttons.
+    fn versus_player(player: usize) -> Bindings {
+        let keys = if player == 0 {
+            [
+                KeyCode::A,
+                KeyCode::D,
+                KeyCode::S,
+                KeyCode::W,
+                KeyCode::E,
+                KeyCode::Q,
+                KeyCode::R,
+                KeyCode::LShift,
+            ]
+        } else {
+            [
+                KeyCode::Left,
+                KeyCode::Right,
+                KeyCode::Down,
+                KeyCode::Up,
+                KeyCode::Period,
+                KeyCode::Comma,
+                KeyCode::Slash,
+                KeyCode::RShift,
+            ]
+        };
+        let mut bindings = Bindings::default();
+        for (action, key) in Action::ALL.into_iter().zip(keys) {
+            bindings.bind(action, key);
+        }
+        bindings
+    }
+
+    /// Reads the bindings file, falling back to the default keys for a missing file and
+    /// for actions the file leaves out.
+    pub fn load() -> Bindings {
+        let mut bindings = Bindings::default();
+        bindings.merge(load_config(BINDINGS_FILE));
+        bindings
+    }
+
+    /// Takes the keys and buttons of every action `loaded` lists, keeping the rest.
+    fn merge(&mut self, loaded: LoadedBindings) {
+        self.keys.extend(loaded.keys);
+        self.buttons.extend(loaded.buttons);
+    }
+
+    pub fn save(&self) {
+        save_config(BINDINGS_FILE, self);
+    }
+
+    pub fn keys(&self, action: Action) -> &[KeyCode] {
+        self.keys.get(&action).map_or(&[], Vec::as_slice)
+    }
+
+    pub fn buttons(&self, action: Action) -> &[GamepadButtonType] {
+        self.buttons.get(&action).map_or(&[], Vec::as_slice)
+    }
+
+    /// Binds `action` to `key` alone, taking the key away from any other action so that
+    /// one press never fires two actions.
+    pub fn bind(&mut self, action: Action, key: KeyCode) {
+        self.unbind(key);
+        self.keys.insert(action, vec![key]);
+    }
+
+    fn unbind(&mut self, key: KeyCode) {
+        for keys in self.keys.values_mut() {
+            keys.retain(|bound| *bound != key);
+        }
+    }
+
+    /// Binds `action` to `button` alone, taking the button away from any other action.
+    pub fn bind_button(&mut self, action: Action, button: GamepadButtonType) {
+        for buttons in self.buttons.values_mut() {
+            buttons.retain(|bound| *bound != button);
+        }
+        self.buttons.insert(action, vec![button]);
+    }
+
+    /// Names of the keys bound to `action`, for showing on screen.
+    pub fn describe(&self, action: Action) -> String {
+        describe_all(self.keys(action))
+    }
+
+    /// Names of the gamepad buttons bound to `action`, for showing on screen.
+    pub fn describe_buttons(&self, action: Action) -> String {
+        describe_all(self.buttons(action))
+    }
+}
+
+/// Bindings as written in a file, where an action left out keeps its default instead of
+/// losing its keys.
+#[derive(Default, Deserialize)]
+#[serde(default)]
+struct LoadedBindings {
+    keys: BTreeMap<Action, Vec<KeyCode>>,
+    buttons: BTreeMap<Action, Vec<GamepadButtonType>>,
+}
+
+fn describe_all(inputs: &[impl std::fmt::Debug]) -> String {
+    if inputs.is_empty() {
+        // Shows an action whose key was taken by another one as unbound.
+        return String::from("-");
+    }
+    let names: Vec<String> = inputs.iter().map(|input| format!("{:?}", input)).collect();
+    names.join(", ")
+}
+
+/// Keys and gamepad buttons of each player in versus, kept in `versus_bindings.ron` in the
+/// config directory. The first connected gamepad plays for the first player and the second
+/// one for the second player, as kept by `ConnectedGamepads`.
+#[derive(Resource, Serialize, Deserialize)]
+#[serde(default)]
+pub struct VersusBindings {
+    pub players: [Bindings; 2],
+}
+
+impl Default for VersusBindings {
+    fn default() -> Self {
+        VersusBindings {
+            players: [Bindings::versus_player(0), Bindings::versus_player(1)],
+        }
+    }
+}
+
+/// Versus bindings as written in a file, each player falling back to their own defaults
+/// like `Bindings::load`.
+#[derive(Default, Deserialize)]
+#[serde(default)]
+struct LoadedVersusBindings {
+    players: [LoadedBindings; 2],
+}
+
+impl VersusBindings {
+    pub fn load() -> VersusBindings {
+        VersusBindings::merged(load_config(VERSUS_BINDINGS_FILE))
+    }
+
+    fn merged(loaded: LoadedVersusBindings) -> VersusBindings {
+        let mut bindings = VersusBindings::default();
+        for (player, loaded) in bindings.players.iter_mut().zip(loaded.players) {
+            player.merge(loaded);
+        }
+        bindings
+    }
+
+    pub fn save(&self) {
+        save_config(VERSUS_BINDINGS_FILE, self);
+    }
+
+    /// Binds `action` of `player` to `key` alone, taking the key away from every other
+    /// action of both players, as both share the keyboard, and from the pause of `shared`.
+    pub fn bind(&mut self, shared: &mut Bindings, player: usize, action: Action, key: KeyCode) {
+        for bindings in self.players.iter_mut() {
+            bindings.unbind(key);
+        }
+        if let Some(keys) = shared.keys.get_mut(&Action::Pause) {
+            keys.retain(|bound| *bound != key);
+        }
+        self.players[player].bind(action, key);
+    }
+}
+
+/// Actions held on `gamepads`, either by a bound button or by tilting the left stick past
+/// `deadzone`. Takes the gamepad resources by reference so that it can be fed with
+/// synthetic button and axis values.
+pub fn gamepad_actions(
+    gamepads: impl IntoIterator<Item = Gamepad>,
+    buttons: &Input<GamepadButton>,
+    axes: &Axis<GamepadAxis>,
+    bindings: &Bindings,
+    deadzone: f32,
+) -> HashSet<Action> {
+    let mut actions = HashSet::new();
+    for gamepad in gamepads {
+        for action in Action::ALL {
+            if bindings
+                .buttons(action)
+                .iter()
+                .any(|button| buttons.pressed(GamepadButton::new(gamepad, *button)))
+            {
+                actions.insert(action);
+            }
+        }
+
+        let stick_x = axes
+            .get(GamepadAxis::new(gamepad, GamepadAxisType::LeftStickX))
+            .unwrap_or_default();
+        let stick_y = axes
+            .get(GamepadAxis::new(gamepad, GamepadAxisType::LeftStickY))
+            .unwrap_or_default();
+        if stick_x < -deadzone {
+            actions.insert(Action::MoveLeft);
+        }
+        if stick_x > deadzone {
+            actions.insert(Action::MoveRight);
+        }
+        if stick_y < -deadzone {
+            actions.insert(Action::SoftDrop);
+        }
+    }
+    actions
+}
+
+/// Translates the keyboard and gamepads into actions, so that pressing any key or button
+/// bound to an action presses the action. Both go through the same repeat timing of the game.
+pub fn read_actions(
+    keyboard_input: Res<Input<KeyCode>>,
+    gamepads: Res<Gamepads>,
+    gamepad_buttons: Res<Input<GamepadButton>>,
+    gamepad_axes: Res<Axis<GamepadAxis>>,
+    bindings: Res<Bindings>,
+    settings: Res<Settings>,
+    mut actions: ResMut<Input<Action>>,
+) {
+    let held_on_gamepad = gamepad_actions(
+        gamepads.iter(),
+        &gamepad_buttons,
+        &gamepad_axes,
+        &bindings,
+        settings.stick_deadzone,
+    );
+    press_actions(&mut actions, &keyboard_input, &bindings, &held_on_gamepad);
+}
+
+/// Gamepads in the order they connected. A gamepad that disconnects leaves its place empty
+/// for the next one to connect, so that the others keep their player.
+#[derive(Resource, Default)]
+pub struct ConnectedGamepads(Vec<Option<Gamepad>>);
+
+impl ConnectedGamepads {
+    fn connect(&mut self, gamepad: Gamepad) {
+        if self.0.contains(&Some(gamepad)) {
+            return;
+        }
+        match self.0.iter_mut().find(|place| place.is_none()) {
+            Some(place) => *place = Some(gamepad),
+            None => self.0.push(Some(gamepad)),
+        }
+    }
+
+    fn disconnect(&mut self, gamepad: Gamepad) {
+        for place in self.0.iter_mut().filter(|place| **place == Some(gamepad)) {
+            *place = None;
+        }
+    }
+
+    /// Gamepad of `player`, if one is connected in their place.
+    pub fn get(&self, player: usize) -> Option<Gamepad> {
+        self.0.get(player).copied().flatten()
+    }
+}
+
+/// Keeps `ConnectedGamepads` up to date as gamepads connect and disconnect.
+pub fn track_gamepads(
+    mut gamepad_events: EventReader<GamepadEvent>,
+    mut connected: ResMut<ConnectedGamepads>,
+) {
+    for event in gamepad_events.iter() {
+        match event.event_type {
+            GamepadEventType::Connected(_) => connected.connect(event.gamepad),
+            GamepadEventType::Disconnected => connected.disconnect(event.gamepad),
+            _ => {}
+        }
+    }
+}
+
+/// Reads the actions of each player in versus from their own keys and gamepad.
+pub fn read_versus_actions(
+    keyboard_input: Res<Input<KeyCode>>,
+    gamepads: Res<ConnectedGamepads>,
+    gamepad_buttons: Res<Input<GamepadButton>>,
+    gamepad_axes: Res<Axis<GamepadAxis>>,
+    bindings: Res<VersusBindings>,
+    settings: Res<Settings>,
+    mut actions: ResMut<VersusActions>,
+) {
+    for (player, (actions, bindings)) in actions.0.iter_mut().zip(&bindings.players).enumerate() {
+        let held_on_gamepad = gamepad_actions(
+            gamepads.get(player),
+            &gamepad_buttons,
+            &gamepad_axes,
+            bindings,
+            settings.stick_deadzone,
+        );
+        press_actions(actions, &keyboard_input, bindings, &held_on_gamepad);
+    }
+}
+
+/// Actions held by each player in versus.
+#[derive(Resource, Default)]
+pub struct VersusActions(pub [Input<Action>; 2]);
+
+fn press_actions(
+    actions: &mut Input<Action>,
+    keyboard_input: &Input<KeyCode>,
+    bindings: &Bindings,
+    held_on_gamepad: &HashSet<Action>,
+) {
+    actions.clear();
+    for action in Action::ALL {
+        if keyboard_input.any_pressed(bindings.keys(action).iter().copied())
+            || held_on_gamepad.contains(&action)
+        {
+            actions.press(action);
+        } else {
+            actions.release(action);
+        }
+    }
+}
+
+/// What the game is asked to do by the actions held.
+pub fn game_inputs(actions: &Input<Action>) -> Inputs {
+    Inputs {
+        left: actions.pressed(Action::MoveLeft),
+        right: actions.pressed(Action::MoveRight),
+        soft_drop: actions.pressed(Action::SoftDrop),
+        hard_drop: actions.pressed(Action::HardDrop),
+        rotate_cw: actions.pressed(Action::RotateCW),
+        rotate_ccw: actions.pressed(Action::RotateCCW),
+        rotate_180: actions.pressed(Action::Rotate180),
+        hold: actions.pressed(Action::Hold),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn binding_takes_the_key_from_other_actions() {
+        let mut bindings = Bindings::default();
+        bindings.bind(Action::MoveLeft, KeyCode::C);
+        bindings.bind_button(Action::Hold, GamepadButtonType::South);
+
+        assert_eq!(bindings.keys(Action::MoveLeft), &[KeyCode::C]);
+        assert_eq!(bindings.keys(Action::Hold), &[KeyCode::LShift]);
+        assert_eq!(bindings.buttons(Action::Hold), &[GamepadButtonType::South]);
+        assert!(bindings.buttons(Action::RotateCW).is_empty());
+    }
+
+    #[test]
+    fn versus_binding_takes_the_key_from_the_shared_pause() {
+        let mut shared = Bindings::default();
+        let mut bindings = VersusBindings::default();
+        bindings.bind(&mut shared, 0, Action::Hold, KeyCode::P);
+
+        assert!(shared.keys(Action::Pause).is_empty());
+        assert_eq!(shared.keys(Action::MoveLeft), &[KeyCode::Left]);
+        assert_eq!(bindings.players[0].keys(Action::Hold), &[KeyCode::P]);
+    }
+
+    #[test]
+    fn versus_binding_takes_the_key_from_the_other_player() {
+        let mut bindings = VersusBindings::default();
+        bindings.bind(&mut Bindings::default(), 1, Action::HardDrop, KeyCode::W);
+
+        assert!(bindings.players[0].keys(Action::HardDrop).is_empty());
+        assert_eq!(bindings.players[1].keys(Action::HardDrop), &[KeyCode::W]);
+    }
+
+    #[test]
+    fn versus_actions_left_out_of_the_file_keep_their_defaults() {
+        let loaded =
+            ron::from_str("(players: ((keys: {HardDrop: [Space]}), (buttons: {Hold: [North]})))")
+                .unwrap();
+        let bindings = VersusBindings::merged(loaded);
+        let defaults = VersusBindings::default();
+
+        assert_eq!(
+            bindings.players[0].keys(Action::HardDrop),
+            &[KeyCode::Space]
+        );
+        assert_eq!(
+            bindings.players[0].keys(Action::MoveLeft),
+            defaults.players[0].keys(Action::MoveLeft)
+        );
+        assert_eq!(
+            bindings.players[1].buttons(Action::Hold),
+            &[GamepadButtonType::North]
+        );
+        assert_eq!(
+            bindings.players[1].keys(Action::Hold),
+            defaults.players[1].keys(Action::Hold)
+        );
+    }
+
+    #[test]
+    fn gamepads_keep_their_player_until_they_disconnect() {
+        let mut connected = ConnectedGamepads::default();
+        connected.connect(Gamepad::new(3));
+        connected.connect(Gamepad::new(1));
+        assert_eq!(connected.get(0), Some(Gamepad::new(3)));
+        assert_eq!(connected.get(1), Some(Gamepad::new(1)));
+
+        connected.disconnect(Gamepad::new(3));
+        connected.connect(Gamepad::new(2));
+        connected.connect(Gamepad::new(1));
+        assert_eq!(connected.get(0), Some(Gamepad::new(2)));
+        assert_eq!(connected.get(1), Some(Gamepad::new(1)));
+        assert_eq!(connected.get(2), None);
+    }
+
+    const GAMEPAD: Gamepad = Gamepad { id: 0 };
+
+    fn held(buttons: &Input<GamepadButton>, axes: &Axis<GamepadAxis>) -> HashSet<Action> {
+        let deadzone = Settings::default().stick_deadzone;
+        gamepad_actions([GAMEPAD], buttons, axes, &Bindings::default(), deadzone)
+    }
+
+    fn tilt(axis_type: GamepadAxisType, value: f32) -> Axis<GamepadAxis> {
+        let mut axes = Axis::default();
+        axes.set(GamepadAxis::new(GAMEPAD, axis_type), value);
+        axes
+    }
+
+    #[test]
+    fn buttons_and_d_pad_press_their_actions() {
+        let mut buttons = Input::default();
+        buttons.press(GamepadButton::new(GAMEPAD, GamepadButtonType::DPadLeft));
+        buttons.press(GamepadButton::new(GAMEPAD, GamepadButtonType::South));
+        buttons.press(GamepadButton::new(GAMEPAD, GamepadButtonType::RightTrigger));
+        // Another gamepad than the one read.
+        buttons.press(GamepadButton::new(
+            Gamepad::new(1),
+            GamepadButtonType::Start,
+        ));
+
+        assert_eq!(
+            held(&buttons, &Axis::default()),
+            HashSet::from([Action::MoveLeft, Action::RotateCW, Action::Hold])
+        );
+    }
+
+    #[test]
+    fn stick_inside_the_deadzone_does_nothing() {
+        let deadzone = Settings::default().stick_deadzone;
+        let buttons = Input::default();
+
+        assert!(held(
+            &buttons,
+            &tilt(GamepadAxisType::LeftStickX, deadzone - 0.01)
+        )
+        .is_empty());
+        assert!(held(
+            &buttons,
+            &tilt(GamepadAxisType::LeftStickY, 0.01 - deadzone)
+        )
+        .is_empty());
+    }
+
+    #[test]
+    fn stick_past_the_deadzone_moves_and_drops() {
+        let deadzone = Settings::default().stick_deadzone;
+        let buttons = Input::default();
+
+        assert_eq!(
+            held(
+                &buttons,
+                &tilt(GamepadAxisType::LeftStickX, deadzone + 0.01)
+            ),
+            HashSet::from([Action::MoveRight])
+        );
+        assert_eq!(
+            held(
+                &buttons,
+                &tilt(GamepadAxisType::LeftStickX, -deadzone - 0.01)
+            ),
+            HashSet::from([Action::MoveLeft])
+        );
+        assert_eq!(
+            held(
+                &buttons,
+                &tilt(GamepadAxisType::LeftStickY, -deadzone - 0.01)
+            ),
+            HashSet::from([Action::SoftDrop])
+        );
+    }
+}
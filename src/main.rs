@@ -1,94 +1,148 @@
-use std::time::Duration;
-
+use bevy::input::InputSystem;
 use bevy::prelude::*;
 use bevy::sprite::MaterialMesh2dBundle;
 use bevy::text::Text2dBounds;
-use derive_more::Constructor;
 use rand::prelude::thread_rng;
 use rand::Rng;
+use tetrominos_core::game::{Game, GameEvent};
+use tetrominos_core::mode::Mode;
+use tetrominos_core::piece::Piece;
+use tetrominos_core::rotation::{RotationSystem, Srs};
 
+use callout::*;
 use game_area::*;
+use input::*;
+use modes::*;
+use online::OnlinePlugin;
 use piece::*;
+use records::*;
+use screens::*;
+use settings::*;
+use stats::*;
+use versus::VersusPlugin;
 
+mod callout;
 mod game_area;
+mod input;
+mod modes;
+mod online;
 mod piece;
+mod records;
+mod screens;
+mod settings;
+mod stats;
+mod versus;
 
 fn main() {
-    App::new()
+    let settings = Settings::from_args().unwrap_or_else(|error| {
+        eprintln!("{}\n\n{}", error, settings::USAGE);
+        std::process::exit(2);
+    });
+    let seed = GameSeed(settings.seed.unwrap_or_else(|| thread_rng().gen()));
+    let game = new_game(&settings, seed);
+    let is_versus = settings.mode == Mode::Versus;
+    let is_online = settings.network.is_some();
+
+    let mut app = App::new();
+    app.insert_resource(ActiveGame(game))
+        .insert_resource(seed)
+        .insert_resource(settings)
+        .insert_resource(Bindings::load())
+        .insert_resource(Records::load())
+        .init_resource::<Splits>()
+        .init_resource::<Placing>()
+        .init_resource::<Input<Action>>()
+        .init_resource::<BindingsCursor>()
         .add_plugins(DefaultPlugins)
-        .add_startup_system(setup)
-        .add_system(first_spawn)
-        .add_event::<ReachedFloorEvent>()
+        .add_system_to_stage(CoreStage::PreUpdate, read_actions.after(InputSystem))
+        .add_state(AppState::MainMenu)
         .add_event::<AreaClearedEvent>()
         .add_event::<NewPositionEvent>()
         .add_event::<NewPieceEvent>()
-        .add_system(descend_piece)
-        .add_system(clear_room.before(descend_piece))
-        .add_system(spawn_on_clear)
-        .add_system(rotate_piece)
-        .add_system(move_sideways)
-        .add_system(draw_piece)
-        .add_system(draw_preview)
-        .add_system(update_score)
-        .add_system(bevy::window::close_on_esc)
-        .run();
+        .add_event::<NewHoldEvent>()
+        .add_event::<CalloutEvent>()
+        .add_system_set(SystemSet::on_enter(AppState::MainMenu).with_system(show_main_menu))
+        .add_system_set(SystemSet::on_update(AppState::MainMenu).with_system(menu_on_key))
+        .add_system_set(SystemSet::on_exit(AppState::MainMenu).with_system(hide_overlay))
+        .add_system_set(SystemSet::on_enter(AppState::Bindings).with_system(reset_bindings_cursor))
+        .add_system_set(
+            SystemSet::on_update(AppState::Bindings)
+                .with_system(rebind_on_key)
+                .with_system(show_bindings.after(rebind_on_key)),
+        )
+        .add_system_set(SystemSet::on_exit(AppState::Bindings).with_system(hide_overlay))
+        .add_system_set(SystemSet::on_enter(AppState::Paused).with_system(show_paused))
+        .add_system_set(SystemSet::on_update(AppState::Paused).with_system(resume_on_action))
+        .add_system_set(SystemSet::on_exit(AppState::Paused).with_system(hide_overlay))
+        .add_system_set(SystemSet::on_update(AppState::GameOver).with_system(restart_on_key))
+        .add_system_set(SystemSet::on_exit(AppState::GameOver).with_system(hide_overlay))
+        .add_system(bevy::window::close_on_esc);
+
+    if is_online {
+        app.add_plugin(OnlinePlugin);
+    } else if is_versus {
+        app.add_plugin(VersusPlugin);
+    } else {
+        add_single_player(&mut app);
+    }
+    app.run();
 }
 
-#[derive(Resource, Constructor)]
-struct Preview {
-    piece: Piece,
-    angle: u8,
+/// Systems of a game played alone, on one board with the stats and mode panels beside it.
+fn add_single_player(app: &mut App) {
+    app.add_startup_system(setup)
+        .add_system_set(SystemSet::on_enter(AppState::Playing).with_system(start_game))
+        .add_system_set(
+            SystemSet::on_update(AppState::Playing)
+                .with_system(play)
+                .with_system(pause_on_action.before(play))
+                .with_system(draw_board.after(play))
+                .with_system(draw_piece.after(play))
+                .with_system(draw_ghost.after(play))
+                .with_system(draw_queue.after(play))
+                .with_system(draw_hold.after(play)),
+        )
+        .add_system_set(SystemSet::on_enter(AppState::GameOver).with_system(show_game_over))
+        .add_system(update_score)
+        .add_system(update_stats)
+        .add_system(update_mode_text)
+        .add_system(show_callouts.after(play))
+        .add_system(expire_callouts);
 }
 
-#[derive(Resource, Constructor)]
-struct PiecePosition {
-    piece: Piece,
-    angle: u8,
-    x: i32,
-    y: i32,
-    is_visible: bool,
+fn new_game(settings: &Settings, seed: GameSeed) -> Game {
+    Game::new(
+        settings.rules(),
+        settings.generator.create(seed.0),
+        Box::new(Srs),
+        seed.0,
+    )
 }
 
-#[derive(Component)]
-struct PreviewSprite;
-
-#[derive(Component)]
-struct PieceSprite;
-
-#[derive(Component)]
-struct ScoreBoard;
-
-#[derive(Component, Constructor)]
-struct RockSprite {
-    x: i32,
-    y: i32,
-    color: Piece,
-}
+#[derive(Resource)]
+struct ActiveGame(Game);
 
-#[derive(Resource, Constructor)]
-struct GameState {
-    level: i32,
-    score: i32,
-    descend_sleep: Duration,
-}
+/// Seed of every random roll in a game, so that a game can be replayed piece by piece.
+#[derive(Resource, Clone, Copy)]
+struct GameSeed(u64);
 
-#[derive(Resource)]
-struct LastDownPress(Duration);
+#[derive(Component, Clone, Copy)]
+struct QueueSprite;
 
-#[derive(Resource)]
-struct LastSidePress(Duration);
+#[derive(Component, Clone, Copy)]
+struct HoldSprite;
 
-#[derive(Resource)]
-struct LastUpPress(bool);
+#[derive(Component)]
+struct PieceSprite;
 
-#[derive(Resource)]
-struct LastSpacePress(bool);
+#[derive(Component)]
+struct GhostSprite;
 
-#[derive(Resource)]
-struct FirstSpawnDone(bool);
+#[derive(Component)]
+struct ScoreBoard;
 
-#[derive(Default)]
-struct ReachedFloorEvent;
+#[derive(Component)]
+struct RockSprite;
 
 #[derive(Default)]
 struct AreaClearedEvent;
@@ -99,21 +153,16 @@ struct NewPositionEvent;
 #[derive(Default)]
 struct NewPieceEvent;
 
-#[derive(Resource)]
-struct GameOver(bool);
-
-#[derive(PartialEq)]
-enum CollisionType {
-    LeftWall,
-    RightWall,
-    Floor,
-    None,
-}
+#[derive(Default)]
+struct NewHoldEvent;
 
 fn setup(
     mut commands: Commands,
     mut meshes: ResMut<Assets<Mesh>>,
     mut materials: ResMut<Assets<ColorMaterial>>,
+    settings: Res<Settings>,
+    game: Res<ActiveGame>,
+    asset_server: Res<AssetServer>,
 ) {
     commands.spawn(Camera2dBundle::default());
     commands.spawn(MaterialMesh2dBundle {
@@ -130,356 +179,141 @@ fn setup(
         ..default()
     });
 
-    commands.spawn(MaterialMesh2dBundle {
-        mesh: meshes.add(Mesh::from(shape::Quad::default())).into(),
-        transform: calculate_transform(
-            PREVIEW_CORNER.x,
-            PREVIEW_CORNER.y,
-            0.1,
-            PREVIEW_AREA.x,
-            PREVIEW_AREA.y,
-        ),
-        material: materials.add(ColorMaterial::from(Color::BLACK)),
-        ..default()
-    });
-
-    commands.insert_resource(Preview::new(
-        Piece::get_random(),
-        thread_rng().gen::<u8>() % 4,
-    ));
-    commands.insert_resource(PiecePosition::new(Piece::O, 0, 0, 0, false));
-    commands.insert_resource(LastDownPress(Duration::from_secs(0)));
-    commands.insert_resource(LastSidePress(Duration::from_secs(0)));
-    commands.insert_resource(LastUpPress(false));
-    commands.insert_resource(LastSpacePress(false));
-    commands.insert_resource(FirstSpawnDone(false));
-    commands.insert_resource(GameOver(false));
-    commands.insert_resource(GameState::new(
-        1,
-        0,
-        Duration::from_millis(INITIAL_DESCEND_SLEEP),
-    ));
-}
-
-fn first_spawn(
-    commands: Commands,
-    asset_server: Res<AssetServer>,
-    position: ResMut<PiecePosition>,
-    preview: ResMut<Preview>,
-    mut first_spawn_done: ResMut<FirstSpawnDone>,
-    new_piece_writer: EventWriter<NewPieceEvent>,
-    game_over: Res<GameOver>,
-) {
-    if !first_spawn_done.0 {
-        first_spawn_done.0 = true;
-        spawn_new_piece(
-            commands,
-            asset_server,
-            position,
-            preview,
-            game_over,
-            new_piece_writer,
-        );
+    let queue_area = queue_area(settings.queue_length);
+    for (corner, area) in [(QUEUE_CORNER, queue_area), (HOLD_CORNER, SLOT_AREA)] {
+        commands.spawn(MaterialMesh2dBundle {
+            mesh: meshes.add(Mesh::from(shape::Quad::default())).into(),
+            transform: calculate_transform(corner.x, corner.y, 0.1, area.x, area.y),
+            material: materials.add(ColorMaterial::from(Color::BLACK)),
+            ..default()
+        });
     }
-}
 
-fn spawn_on_clear(
-    commands: Commands,
-    asset_server: Res<AssetServer>,
-    position: ResMut<PiecePosition>,
-    preview: ResMut<Preview>,
-    area_cleared_reader: EventReader<AreaClearedEvent>,
-    new_piece_writer: EventWriter<NewPieceEvent>,
-    game_over: Res<GameOver>,
-) {
-    if !area_cleared_reader.is_empty() {
-        area_cleared_reader.clear();
-        spawn_new_piece(
-            commands,
-            asset_server,
-            position,
-            preview,
-            game_over,
-            new_piece_writer,
-        );
-    }
+    spawn_stats_panel(&mut commands, &asset_server, &game);
+    spawn_mode_text(&mut commands, &asset_server);
 }
 
-fn spawn_new_piece(
-    mut commands: Commands,
-    asset_server: Res<AssetServer>,
-    mut position: ResMut<PiecePosition>,
-    mut preview: ResMut<Preview>,
-    game_over: Res<GameOver>,
+#[allow(clippy::too_many_arguments)]
+fn play(
+    mut game: ResMut<ActiveGame>,
+    actions: Res<Input<Action>>,
+    time: Res<Time>,
+    mut area_cleared_writer: EventWriter<AreaClearedEvent>,
+    mut new_position_writer: EventWriter<NewPositionEvent>,
     mut new_piece_writer: EventWriter<NewPieceEvent>,
+    mut new_hold_writer: EventWriter<NewHoldEvent>,
+    mut callout_writer: EventWriter<CalloutEvent>,
+    mut splits: ResMut<Splits>,
+    mut records: ResMut<Records>,
+    mut placing: ResMut<Placing>,
+    mut app_state: ResMut<State<AppState>>,
 ) {
-    if game_over.0 {
-        return;
-    }
-    position.piece = preview.piece.clone();
-    position.angle = preview.angle.clone();
-    position.x = HORIZONTAL_TILES as i32 / 2 - 1;
-    position.y = -5;
-    position.is_visible = true;
-
-    preview.piece = Piece::get_random();
-    preview.angle = thread_rng().gen::<u8>() % 4;
-
-    let mut new_tiles = position
-        .piece
-        .get_tiles(position.angle, position.x, position.y);
-
-    loop {
-        let offer_tiles = position
-            .piece
-            .get_tiles(position.angle, position.x, position.y + 1);
-        let mut is_visible = false;
-        for i in 0..new_tiles.len() {
-            if offer_tiles[i].1 >= 0 {
-                is_visible = true;
-                break;
+    game.0.step(game_inputs(&actions), time.delta());
+
+    for event in game.0.drain_events() {
+        match event {
+            GameEvent::NewPiece => new_piece_writer.send_default(),
+            GameEvent::NewPosition => new_position_writer.send_default(),
+            GameEvent::Hold => new_hold_writer.send_default(),
+            GameEvent::Locked(_) => {}
+            GameEvent::TSpin(spin, lines) => callout_writer.send(t_spin_callout(spin, lines)),
+            GameEvent::LinesCleared(lines) => {
+                let elapsed = game.0.elapsed();
+                splits.0.extend((0..lines).map(|_| elapsed));
+                area_cleared_writer.send_default();
+            }
+            GameEvent::BackToBack(chain) => callout_writer.send(back_to_back_callout(chain)),
+            GameEvent::Combo(combo) => {
+                callout_writer.send(CalloutEvent(format!("COMBO {}", combo)))
+            }
+            GameEvent::PerfectClear => callout_writer.send(CalloutEvent("PERFECT CLEAR".into())),
+            GameEvent::GarbageAdded { .. } => {
+                // The stack moved under the piece, so its ghost has to follow.
+                area_cleared_writer.send_default();
+                new_position_writer.send_default();
+            }
+            GameEvent::Attack(_) => {}
+            GameEvent::LevelUp(level) => info!("Level {}", level),
+            GameEvent::GameOver(ending) => {
+                info!("Game over: {:?}", ending);
+                *placing = submit_results(&game, &mut records, &splits);
+                if placing.is_placed() {
+                    records.save();
+                }
+                app_state.overwrite_set(AppState::GameOver).unwrap();
             }
         }
-        if is_visible {
-            break;
-        }
-        position.y = position.y + 1;
-        new_tiles = offer_tiles;
-    }
-
-    place_piece(&mut commands, asset_server, position, new_tiles);
-
-    new_piece_writer.send_default();
-}
-
-fn place_piece(
-    commands: &mut Commands,
-    asset_server: Res<AssetServer>,
-    position: ResMut<PiecePosition>,
-    new_tiles: Vec<(i32, i32)>,
-) {
-    for i in 0..new_tiles.len() {
-        let tile = new_tiles[i];
-        commands.spawn((
-            PieceSprite,
-            SpriteBundle {
-                texture: position.piece.get_image(&asset_server),
-                visibility: Visibility {
-                    is_visible: tile.1 >= 0,
-                },
-                transform: tile_transform(tile),
-                ..default()
-            },
-        ));
     }
 }
 
-fn descend_piece(
+fn draw_board(
     mut commands: Commands,
-    mut position: ResMut<PiecePosition>,
+    game: Res<ActiveGame>,
+    sprite_query: Query<(&RockSprite, Entity)>,
+    area_cleared_reader: EventReader<AreaClearedEvent>,
     asset_server: Res<AssetServer>,
-    game_state: Res<GameState>,
-    mut game_over: ResMut<GameOver>,
-    mut last_click: ResMut<LastDownPress>,
-    keyboard_input: Res<Input<KeyCode>>,
-    mut last_space: ResMut<LastSpacePress>,
-    time: Res<Time>,
-    rock_query: Query<(&RockSprite, Entity)>,
-    mut reached_floor_writer: EventWriter<ReachedFloorEvent>,
-    mut new_position_writer: EventWriter<NewPositionEvent>,
 ) {
-    if game_over.0 {
-        return;
-    }
-
-    let mut space_pressed = false;
-    if last_space.0 && !keyboard_input.pressed(KeyCode::Space) {
-        last_space.0 = false;
-    } else if !last_space.0 && keyboard_input.pressed(KeyCode::Space) {
-        space_pressed = true;
-        last_space.0 = true;
-    }
-
-    let since_click = time.elapsed() - last_click.0;
-    if space_pressed
-        || since_click >= game_state.descend_sleep
-        || (keyboard_input.pressed(KeyCode::Down)
-            && since_click >= Duration::from_millis(DOWN_MOVE_SLEEP))
-    {
-        last_click.0 = time.elapsed();
-
-        let rocks_entities: Vec<(&RockSprite, Entity)> = rock_query.iter().collect();
-        let rocks: Vec<&RockSprite> = rocks_entities.iter().map(|pair| pair.0).collect();
-        loop {
-            let new_y = position.y + 1;
-
-            if collision(
-                &position.piece,
-                &position.angle,
-                &position.x,
-                &new_y,
-                &rocks,
-            ) == CollisionType::Floor
-            {
-                info!("Floor at x={} y={}", position.x, position.y);
-
-                let mut rock_out_of_bounds = false;
-                for (x, y) in position
-                    .piece
-                    .get_tiles(position.angle, position.x, position.y)
-                {
-                    if y < 0 {
-                        rock_out_of_bounds = true;
-                    }
-                    spawn_rock(&mut commands, &asset_server, x, y, &position.piece);
-                }
-
-                if rock_out_of_bounds {
-                    game_over.0 = true;
-                    info!("Game Over!");
-                }
-
-                position.is_visible = false;
-                reached_floor_writer.send_default();
+    if !area_cleared_reader.is_empty() {
+        area_cleared_reader.clear();
 
-                break;
-            } else {
-                position.y = new_y;
-                new_position_writer.send_default();
-                info!("New x={} y={}", position.x, new_y);
-            }
+        sprite_query.for_each(|(_, entity)| {
+            commands.entity(entity).despawn();
+        });
 
-            if !space_pressed {
-                break;
-            }
+        for (x, y, block) in game.0.board().cells() {
+            commands.spawn((
+                RockSprite,
+                SpriteBundle {
+                    sprite: Sprite {
+                        color: block.tint(),
+                        ..default()
+                    },
+                    texture: block.get_image(&asset_server),
+                    transform: tile_transform((x, y)),
+                    ..default()
+                },
+            ));
         }
     }
 }
 
-fn spawn_rock(
-    commands: &mut Commands,
-    asset_server: &Res<AssetServer>,
-    x: i32,
-    y: i32,
-    color: &Piece,
-) {
-    commands.spawn((
-        RockSprite::new(x, y, color.clone()),
-        SpriteBundle {
-            texture: color.get_image(&asset_server),
-            transform: tile_transform((x, y)),
-            visibility: Visibility { is_visible: y >= 0 },
-            ..default()
-        },
-    ));
-}
-
-fn clear_room(
+fn draw_piece(
     mut commands: Commands,
+    game: Res<ActiveGame>,
+    sprite_query: Query<(&PieceSprite, Entity)>,
+    new_position_reader: EventReader<NewPositionEvent>,
     asset_server: Res<AssetServer>,
-    mut game_state: ResMut<GameState>,
-    mut rock_query: Query<(&RockSprite, Entity)>,
-    reached_floor_reader: EventReader<ReachedFloorEvent>,
-    mut area_cleared_writer: EventWriter<AreaClearedEvent>,
 ) {
-    if !reached_floor_reader.is_empty() {
-        reached_floor_reader.clear();
-
-        let rocks = rock_query
-            .iter_mut()
-            .collect::<Vec<(&RockSprite, Entity)>>();
-
-        let mut line_edits = vec![0; VERTICAL_TILES as usize];
-        let mut line_counts = vec![0; VERTICAL_TILES as usize];
-        let mut cleared = 0;
-
-        for i in 0..rocks.len() {
-            let rock = rocks[i].0;
-            if rock.y >= 0 {
-                let y = rock.y as usize;
-                line_counts[y] += 1;
-                if line_counts[y] == HORIZONTAL_TILES {
-                    line_edits[y] = -1;
-                    cleared += 1;
-                }
-            }
-        }
+    if !new_position_reader.is_empty() {
+        new_position_reader.clear();
 
-        game_state.score += game_state.level
-            * match cleared {
-                0 => 0,
-                1 => 100,
-                2 => 300,
-                3 => 500,
-                4 => 800,
-                _ => 0,
-            };
-
-        let mut remove = 0;
-        for i in (0..VERTICAL_TILES as usize).rev() {
-            if line_edits[i] == -1 {
-                remove += 1;
-            } else {
-                line_edits[i] = remove;
-            }
-        }
+        sprite_query.for_each(|(_, entity)| {
+            commands.entity(entity).despawn();
+        });
 
-        for i in 0..rocks.len() {
-            let rock = rocks[i].0;
-            let entity = rocks[i].1;
-            if rock.y >= 0 {
-                let edit = line_edits[rock.y as usize];
-                if edit == -1 {
-                    commands.entity(entity).despawn();
-                } else if edit > 0 {
-                    commands.entity(entity).despawn();
-                    let x = rock.x;
-                    let y = rock.y + edit;
-                    spawn_rock(&mut commands, &asset_server, x, y, &rock.color);
-                }
+        if let Some(position) = game.0.position() {
+            for tile in game.0.piece_tiles() {
+                commands.spawn((
+                    PieceSprite,
+                    SpriteBundle {
+                        texture: position.piece.get_image(&asset_server),
+                        visibility: Visibility {
+                            is_visible: tile.1 >= 0,
+                        },
+                        transform: tile_transform(tile),
+                        ..default()
+                    },
+                ));
             }
         }
-
-        area_cleared_writer.send_default();
     }
 }
 
-fn collision(
-    piece: &Piece,
-    angle: &u8,
-    x: &i32,
-    y: &i32,
-    rocks: &Vec<&RockSprite>,
-) -> CollisionType {
-    let new_coords = piece.get_tiles(*angle, *x, *y);
-
-    for (new_x, new_y) in new_coords {
-        if new_y >= VERTICAL_TILES as i32 {
-            return CollisionType::Floor;
-        }
-
-        for rock in rocks {
-            if new_x == rock.x && new_y == rock.y {
-                return CollisionType::Floor;
-            }
-        }
-
-        if new_x < 0 {
-            return CollisionType::LeftWall;
-        }
-
-        if new_x >= HORIZONTAL_TILES as i32 {
-            return CollisionType::RightWall;
-        }
-    }
-
-    return CollisionType::None;
-}
-
-fn draw_piece(
+fn draw_ghost(
     mut commands: Commands,
-    position: ResMut<PiecePosition>,
-    sprite_query: Query<(&PieceSprite, Entity)>,
+    game: Res<ActiveGame>,
+    settings: Res<Settings>,
+    sprite_query: Query<(&GhostSprite, Entity)>,
     new_position_reader: EventReader<NewPositionEvent>,
     asset_server: Res<AssetServer>,
 ) {
@@ -490,19 +324,38 @@ fn draw_piece(
             commands.entity(entity).despawn();
         });
 
-        if position.is_visible {
-            let coords = position
-                .piece
-                .get_tiles(position.angle, position.x, position.y);
-            place_piece(&mut commands, asset_server, position, coords);
+        if !settings.show_ghost {
+            return;
+        }
+
+        if let Some(position) = game.0.position() {
+            for tile in game.0.ghost_tiles() {
+                let mut transform = tile_transform(tile);
+                transform.translation.z = 0.5;
+                commands.spawn((
+                    GhostSprite,
+                    SpriteBundle {
+                        sprite: Sprite {
+                            color: Color::rgba(1., 1., 1., GHOST_ALPHA),
+                            ..default()
+                        },
+                        texture: position.piece.get_image(&asset_server),
+                        visibility: Visibility {
+                            is_visible: tile.1 >= 0,
+                        },
+                        transform,
+                        ..default()
+                    },
+                ));
+            }
         }
     }
 }
 
-fn draw_preview(
+fn draw_queue(
     mut commands: Commands,
-    preview: Res<Preview>,
-    sprite_query: Query<(&PreviewSprite, Entity)>,
+    game: Res<ActiveGame>,
+    sprite_query: Query<(&QueueSprite, Entity)>,
     new_piece_reader: EventReader<NewPieceEvent>,
     asset_server: Res<AssetServer>,
 ) {
@@ -513,153 +366,101 @@ fn draw_preview(
             commands.entity(entity).despawn();
         });
 
-        let tiles = preview.piece.get_tiles(preview.angle, 0, 0);
-
-        let mut max_x = i32::MIN;
-        let mut max_y = i32::MIN;
-        let mut min_x = i32::MAX;
-        let mut min_y = i32::MAX;
-
-        for i in 0..tiles.len() {
-            max_x = max_x.max(tiles[i].0);
-            max_y = max_y.max(tiles[i].1);
-            min_x = min_x.min(tiles[i].0);
-            min_y = min_y.min(tiles[i].1);
-        }
-
-        let d_left = MARGIN + TILE_SIZE * min_x as f32;
-        let d_top = MARGIN + TILE_SIZE * min_y as f32;
-        let d_right = MARGIN + TILE_SIZE * (PREVIEW_TILES - max_x - 1) as f32;
-        let d_bottom = MARGIN + TILE_SIZE * (PREVIEW_TILES - max_y - 1) as f32;
-
-        let horizontal_margin = (d_left + d_right) / 2.0;
-        let vertical_margin = (d_top + d_bottom) / 2.0;
-
-        for i in 0..tiles.len() {
-            let tile = tiles[i];
-            commands.spawn((
-                PreviewSprite,
-                SpriteBundle {
-                    texture: preview.piece.get_image(&asset_server),
-                    transform: preview_tile_translation(
-                        tile,
-                        horizontal_margin - d_left,
-                        vertical_margin - d_top,
-                    ),
-                    ..default()
-                },
-            ));
+        for (index, piece) in game.0.queue().iter().enumerate() {
+            let (corner, tile_size) = queue_slot(QUEUE_CORNER, index);
+            place_panel_piece(
+                &mut commands,
+                &asset_server,
+                game.0.rotation(),
+                piece,
+                corner,
+                tile_size,
+                QueueSprite,
+            );
         }
     }
 }
 
-fn rotate_piece(
-    mut position: ResMut<PiecePosition>,
-    keyboard_input: Res<Input<KeyCode>>,
-    rock_query: Query<(&RockSprite, Entity)>,
-    mut last_click: ResMut<LastUpPress>,
-    mut new_position_writer: EventWriter<NewPositionEvent>,
+fn draw_hold(
+    mut commands: Commands,
+    game: Res<ActiveGame>,
+    sprite_query: Query<(&HoldSprite, Entity)>,
+    new_hold_reader: EventReader<NewHoldEvent>,
+    asset_server: Res<AssetServer>,
 ) {
-    if last_click.0 && !keyboard_input.pressed(KeyCode::Up) {
-        last_click.0 = false;
-    } else if !last_click.0 && keyboard_input.pressed(KeyCode::Up) {
-        last_click.0 = true;
-        let new_angle = (position.angle + 1) % 4;
-        let mut new_x = position.x;
-
-        let rocks: Vec<&RockSprite> = rock_query.iter().map(|pair| pair.0).collect();
-
-        let mut collision_type = collision(
-            &position.piece,
-            &new_angle,
-            &position.x,
-            &position.y,
-            &rocks,
-        );
-
-        let mut accepted = false;
-        if collision_type == CollisionType::None {
-            position.angle = new_angle;
-            accepted = true;
-        } else if collision_type == CollisionType::RightWall {
-            new_x = position.x - 1;
-            collision_type = collision(&position.piece, &new_angle, &new_x, &position.y, &rocks);
-            if collision_type == CollisionType::None {
-                accepted = true;
-            } else {
-                new_x = position.x - 2;
-                collision_type =
-                    collision(&position.piece, &new_angle, &new_x, &position.y, &rocks);
-                if collision_type == CollisionType::None {
-                    accepted = true;
-                }
-            }
-        } else if collision_type == CollisionType::LeftWall {
-            new_x = position.x + 1;
-            collision_type = collision(&position.piece, &new_angle, &new_x, &position.y, &rocks);
-            if collision_type == CollisionType::None {
-                accepted = true;
-            } else {
-                new_x = position.x + 2;
-                collision_type =
-                    collision(&position.piece, &new_angle, &new_x, &position.y, &rocks);
-                if collision_type == CollisionType::None {
-                    accepted = true;
-                }
-            }
-        }
+    if !new_hold_reader.is_empty() {
+        new_hold_reader.clear();
 
-        if accepted {
-            position.angle = new_angle;
-            position.x = new_x;
-            new_position_writer.send_default();
+        sprite_query.for_each(|(_, entity)| {
+            commands.entity(entity).despawn();
+        });
+
+        if let Some(piece) = game.0.hold() {
+            place_panel_piece(
+                &mut commands,
+                &asset_server,
+                game.0.rotation(),
+                &piece,
+                HOLD_CORNER,
+                TILE_SIZE,
+                HoldSprite,
+            );
         }
     }
 }
 
-fn move_sideways(
-    mut position: ResMut<PiecePosition>,
-    keyboard_input: Res<Input<KeyCode>>,
-    rock_query: Query<(&RockSprite, Entity)>,
-    time: Res<Time>,
-    mut last_click: ResMut<LastSidePress>,
-    mut new_position_writer: EventWriter<NewPositionEvent>,
+fn place_panel_piece(
+    commands: &mut Commands,
+    asset_server: &Res<AssetServer>,
+    rotation: &dyn RotationSystem,
+    piece: &Piece,
+    corner: Vec2,
+    tile_size: f32,
+    marker: impl Component + Copy,
 ) {
-    let since_click = time.elapsed() - last_click.0;
-    if since_click < Duration::from_millis(LEFT_RIGHT_MOVE_SLEEP) {
-        return;
+    let tiles = rotation.get_tiles(piece, 0, 0, 0);
+
+    let mut max_x = i32::MIN;
+    let mut max_y = i32::MIN;
+    let mut min_x = i32::MAX;
+    let mut min_y = i32::MAX;
+
+    for tile in tiles.iter() {
+        max_x = max_x.max(tile.0);
+        max_y = max_y.max(tile.1);
+        min_x = min_x.min(tile.0);
+        min_y = min_y.min(tile.1);
     }
 
-    let mut delta_x = 0;
-    if keyboard_input.pressed(KeyCode::Left) && !keyboard_input.pressed(KeyCode::Right) {
-        delta_x = -1;
-    } else if keyboard_input.pressed(KeyCode::Right) && !keyboard_input.pressed(KeyCode::Left) {
-        delta_x = 1;
-    }
+    let d_left = MARGIN + tile_size * min_x as f32;
+    let d_top = MARGIN + tile_size * min_y as f32;
+    let d_right = MARGIN + tile_size * (SLOT_TILES - max_x - 1) as f32;
+    let d_bottom = MARGIN + tile_size * (SLOT_TILES - max_y - 1) as f32;
 
-    if delta_x != 0 {
-        let new_x = position.x + delta_x;
-
-        let rocks: Vec<&RockSprite> = rock_query.iter().map(|pair| pair.0).collect();
-
-        if collision(
-            &position.piece,
-            &position.angle,
-            &new_x,
-            &position.y,
-            &rocks,
-        ) == CollisionType::None
-        {
-            position.x = new_x;
-            last_click.0 = time.elapsed();
-            new_position_writer.send_default();
-        }
+    let horizontal_margin = (d_left + d_right) / 2.0;
+    let vertical_margin = (d_top + d_bottom) / 2.0;
+
+    for tile in tiles {
+        commands.spawn((
+            marker,
+            SpriteBundle {
+                texture: piece.get_image(asset_server),
+                transform: panel_tile_translation(
+                    corner,
+                    tile_size,
+                    tile,
+                    horizontal_margin - d_left,
+                    vertical_margin - d_top,
+                ),
+                ..default()
+            },
+        ));
     }
 }
 
 fn update_score(
     mut commands: Commands,
-    game_state: Res<GameState>,
+    game: Res<ActiveGame>,
     mut score_board_query: Query<(&ScoreBoard, Entity)>,
     asset_server: Res<AssetServer>,
 ) {
@@ -675,15 +476,15 @@ fn update_score(
     commands.spawn((
         ScoreBoard,
         Text2dBundle {
-            text: Text::from_section(game_state.score.to_string(), text_style)
+            text: Text::from_section(game.0.state().score.to_string(), text_style)
                 .with_alignment(TextAlignment::CENTER),
             text_2d_bounds: Text2dBounds {
                 size: Vec2::new(SCORE_BOARD_WIDTH, SCORE_BOARD_HEIGHT),
             },
             transform: Transform {
                 translation: calculate_translation(
-                    PREVIEW_CORNER.x,
-                    PREVIEW_CORNER.y + (1. + PREVIEW_TILES as f32) * TILE_SIZE + 2. * MARGIN,
+                    SCORE_CORNER.x,
+                    SCORE_CORNER.y,
                     2.,
                     SCORE_BOARD_WIDTH,
                     SCORE_BOARD_HEIGHT,
@@ -0,0 +1,268 @@
+use std::time::Duration;
+
+use bevy::prelude::*;
+use bevy::text::Text2dBounds;
+use tetrominos_core::mode::{Ending, Mode};
+
+use crate::game_area::*;
+use crate::records::{
+    split_delta, CheeseRecord, Placing, Records, Splits, SprintRecord, UltraRecord,
+};
+use crate::stats::{format_precise_time, format_time};
+use crate::ActiveGame;
+
+/// Top results listed when an ultra ends.
+const ULTRA_LEADERBOARD_SHOWN: usize = 3;
+
+/// Progress towards the goal of the mode, under the score.
+#[derive(Component)]
+pub struct ModeText;
+
+pub fn mode_name(mode: Mode) -> String {
+    match mode {
+        Mode::Marathon => String::from("MARATHON"),
+        Mode::Sprint { lines } => format!("SPRINT {} LINES", lines),
+        Mode::Ultra { duration } => format!("ULTRA {}", format_time(duration)),
+        Mode::Cheese { rows } => format!("CHEESE {} ROWS", rows),
+        Mode::Survival => String::from("SURVIVAL"),
+        Mode::Versus => String::from("VERSUS"),
+    }
+}
+
+pub fn spawn_mode_text(commands: &mut Commands, asset_server: &Res<AssetServer>) {
+    let font = asset_server.load("fonts/FiraSans-Bold.ttf");
+    let text_style = TextStyle {
+        font,
+        font_size: MODE_FONT_SIZE,
+        color: Color::WHITE,
+    };
+    commands.spawn((
+        ModeText,
+        Text2dBundle {
+            text: Text::from_section("", text_style).with_alignment(TextAlignment::CENTER),
+            text_2d_bounds: Text2dBounds { size: MODE_AREA },
+            transform: Transform {
+                translation: calculate_translation(
+                    MODE_CORNER.x,
+                    MODE_CORNER.y,
+                    2.,
+                    MODE_AREA.x,
+                    MODE_AREA.y,
+                ),
+                ..default()
+            },
+            ..default()
+        },
+    ));
+}
+
+pub fn update_mode_text(
+    game: Res<ActiveGame>,
+    records: Res<Records>,
+    splits: Res<Splits>,
+    mut text_query: Query<&mut Text, With<ModeText>>,
+) {
+    let value = match game.0.mode() {
+        Mode::Marathon | Mode::Versus => String::new(),
+        Mode::Sprint { lines } => {
+            let left = (lines - game.0.state().lines).max(0);
+            match records
+                .sprint(lines)
+                .and_then(|record| split_delta(&splits, record))
+            {
+                Some(delta) => format!("{} left\n{:+.1}", left, delta),
+                None => format!("{} left", left),
+            }
+        }
+        Mode::Ultra { .. } => format_time(game.0.remaining().unwrap_or_default()),
+        Mode::Cheese { .. } => format!("{} garbage left", game.0.board().garbage_rows()),
+        Mode::Survival => format!(
+            "{}\nNext row {:.1}",
+            format_time(game.0.elapsed()),
+            game.0.next_garbage().unwrap_or_default().as_secs_f32()
+        ),
+    };
+
+    for mut text in text_query.iter_mut() {
+        text.sections[0].value = value.clone();
+    }
+}
+
+/// Submits a finished game to the records of its mode. An ultra counts however it ended, its
+/// score being the result even when it topped out before the time ran out.
+pub fn submit_results(game: &ActiveGame, records: &mut Records, splits: &Splits) -> Placing {
+    let state = game.0.state();
+    let statistics = game.0.statistics();
+    let elapsed = game.0.elapsed();
+
+    match (game.0.mode(), game.0.ending()) {
+        (Mode::Sprint { lines }, Some(Ending::Completed)) => Placing {
+            is_best: records.submit_sprint(
+                lines,
+                SprintRecord {
+                    time: elapsed,
+                    pieces: statistics.pieces,
+                    splits: splits.0.clone(),
+                },
+            ),
+            rank: None,
+        },
+        (Mode::Ultra { duration }, Some(_)) => Placing {
+            is_best: false,
+            rank: records.submit_ultra(
+                duration,
+                UltraRecord {
+                    score: state.score,
+                    lines: state.lines,
+                    pieces: statistics.pieces,
+                },
+            ),
+        },
+        (Mode::Cheese { rows }, Some(Ending::Completed)) => Placing {
+            is_best: records.submit_cheese(
+                rows,
+                CheeseRecord {
+                    time: elapsed,
+                    pieces: statistics.pieces,
+                },
+            ),
+            rank: None,
+        },
+        _ => Placing::default(),
+    }
+}
+
+/// Summary of a finished game, along with how it placed on the records.
+pub fn results_message(game: &ActiveGame, records: &Records, placing: &Placing) -> String {
+    let state = game.0.state();
+    let statistics = game.0.statistics();
+    let elapsed = game.0.elapsed();
+
+    match (game.0.mode(), game.0.ending()) {
+        (Mode::Sprint { lines }, Some(Ending::Completed)) => {
+            let best = best_message(placing, records.sprint(lines).map(|record| record.time));
+            format!(
+                "SPRINT COMPLETE\n\nTime {}\nPPS {:.2}\nPieces {}\nFinesse faults {}\n\n{}",
+                format_precise_time(elapsed),
+                statistics.pieces_per_second(elapsed),
+                statistics.pieces,
+                statistics.finesse_faults,
+                best
+            )
+        }
+        (Mode::Ultra { duration }, ending) => {
+            let leaderboard: Vec<String> = records
+                .ultra(duration)
+                .iter()
+                .take(ULTRA_LEADERBOARD_SHOWN)
+                .enumerate()
+                .map(|(index, record)| format!("{}. {}", index + 1, record.score))
+                .collect();
+            format!(
+                "{}\n\nScore {}\nLines {}\nPieces {}\nPPS {:.2}\n\n{}\n{}",
+                if ending == Some(Ending::TimeUp) {
+                    "TIME UP"
+                } else {
+                    "TOPPED OUT"
+                },
+                state.score,
+                state.lines,
+                statistics.pieces,
+                statistics.pieces_per_second(elapsed),
+                placing
+                    .rank
+                    .map_or(String::from("Not ranked"), |rank| format!("Rank {}", rank)),
+                leaderboard.join("\n")
+            )
+        }
+        (Mode::Cheese { rows }, Some(Ending::Completed)) => {
+            let best = best_message(placing, records.cheese(rows).map(|record| record.time));
+            format!(
+                "CHEESE CLEARED\n\nTime {}\nPieces {}\nPPS {:.2}\nFinesse faults {}\n\n{}",
+                format_precise_time(elapsed),
+                statistics.pieces,
+                statistics.pieces_per_second(elapsed),
+                statistics.finesse_faults,
+                best
+            )
+        }
+        (Mode::Survival, ending) => format!(
+            "{}\n\nSurvived {}\nLines {}\nGarbage rows {}\nPieces {}\nPPS {:.2}",
+            if ending == Some(Ending::Buried) {
+                "BURIED"
+            } else {
+                "TOPPED OUT"
+            },
+            format_time(elapsed),
+            state.lines,
+            statistics.garbage_rows,
+            statistics.pieces,
+            statistics.pieces_per_second(elapsed)
+        ),
+        _ => format!(
+            "GAME OVER\n\nScore {}\nLevel {}\nLines {}\nTime {}\nPieces {}\nPPS {:.2}\nTetrises {}",
+            state.score,
+            state.level,
+            state.lines,
+            format_time(elapsed),
+            statistics.pieces,
+            statistics.pieces_per_second(elapsed),
+            statistics.clears_of(4)
+        ),
+    }
+}
+
+/// Line telling a timed run it set a new best, or what the best to beat is.
+fn best_message(placing: &Placing, best: Option<Duration>) -> String {
+    match (placing.is_best, best) {
+        (true, _) => String::from("NEW PERSONAL BEST"),
+        (false, Some(time)) => format!("Best {}", format_precise_time(time)),
+        (false, None) => String::new(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tetrominos_core::game::{Game, Inputs, Rules};
+    use tetrominos_core::generator::GeneratorKind;
+    use tetrominos_core::rotation::Srs;
+
+    use super::*;
+
+    const DURATION: Duration = Duration::from_secs(120);
+
+    /// Hard drops every piece in the middle until the ultra tops out.
+    fn topped_out_ultra() -> ActiveGame {
+        let mut game = Game::new(
+            Rules {
+                mode: Mode::Ultra { duration: DURATION },
+                ..Rules::default()
+            },
+            GeneratorKind::SevenBag.create(0),
+            Box::new(Srs),
+            0,
+        );
+        let hard_drop = Inputs {
+            hard_drop: true,
+            ..Inputs::default()
+        };
+        while !game.is_over() {
+            game.step(hard_drop, Duration::from_millis(10));
+            game.step(Inputs::default(), Duration::from_millis(10));
+        }
+        ActiveGame(game)
+    }
+
+    #[test]
+    fn ultra_that_tops_out_is_ranked_by_its_score() {
+        let game = topped_out_ultra();
+        assert_eq!(game.0.ending(), Some(Ending::ToppedOut));
+        let mut records = Records::default();
+
+        let placing = submit_results(&game, &mut records, &Splits::default());
+
+        assert_eq!(placing.rank, Some(1));
+        assert_eq!(records.ultra(DURATION)[0].score, game.0.state().score);
+        assert!(results_message(&game, &records, &placing).starts_with("TOPPED OUT"));
+    }
+}
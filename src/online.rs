@@ -0,0 +1,333 @@
+use std::collections::HashSet;
+use std::time::Duration;
+
+use bevy::prelude::*;
+use rand::prelude::thread_rng;
+use rand::Rng;
+use tetrominos_core::game::Game;
+use tetrominos_core::net::{Host, Join, OnlineMatch};
+use tetrominos_core::rotation::Srs;
+
+use crate::game_area::*;
+use crate::input::{game_inputs, Action};
+use crate::screens::{hide_overlay, spawn_overlay, AppState, Overlay, BACK_KEY};
+use crate::settings::NetworkRole;
+use crate::stats::format_time;
+use crate::versus::*;
+use crate::{GameSeed, Settings};
+
+/// How long joining waits for the host to answer before giving up.
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Versus against someone on another computer, the local board on the left and the
+/// opponent's, as their messages tell it, on the right.
+pub struct OnlinePlugin;
+
+impl Plugin for OnlinePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Lobby>()
+            .init_resource::<LobbyStatus>()
+            .init_resource::<OnlineGame>()
+            .add_event::<FieldChangedEvent>()
+            .add_startup_system(setup_online)
+            .add_system_set(SystemSet::on_enter(AppState::Lobby).with_system(open_lobby))
+            .add_system_set(
+                SystemSet::on_update(AppState::Lobby)
+                    .with_system(wait_for_opponent)
+                    .with_system(show_lobby_status.after(wait_for_opponent)),
+            )
+            .add_system_set(SystemSet::on_exit(AppState::Lobby).with_system(hide_overlay))
+            .add_system_set(SystemSet::on_enter(AppState::Playing).with_system(start_online))
+            .add_system_set(
+                SystemSet::on_update(AppState::Playing)
+                    .with_system(play_online)
+                    .with_system(draw_online_fields.after(play_online)),
+            )
+            .add_system_set(
+                SystemSet::on_enter(AppState::GameOver).with_system(show_online_results),
+            );
+    }
+}
+
+/// The listener of a host, kept between matches, or the connection of a joiner waiting for
+/// the match to start.
+#[derive(Resource, Default)]
+struct Lobby {
+    host: Option<Host>,
+    join: Option<Join>,
+}
+
+/// What the lobby screen tells the player.
+#[derive(Resource, Default)]
+struct LobbyStatus(String);
+
+#[derive(Resource, Default)]
+struct OnlineGame {
+    online: Option<OnlineMatch>,
+    /// Why the match ended early, if the connection failed.
+    error: Option<String>,
+    /// Opponent updates drawn so far.
+    drawn_updates: u32,
+}
+
+fn setup_online(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+) {
+    spawn_versus_background(&mut commands, &mut meshes, &mut materials);
+    spawn_field(&mut commands, &mut meshes, &mut materials, 0, true);
+    spawn_field(&mut commands, &mut meshes, &mut materials, 1, false);
+}
+
+/// Drops the previous match and starts listening or connecting, depending on the role.
+fn open_lobby(
+    mut lobby: ResMut<Lobby>,
+    mut status: ResMut<LobbyStatus>,
+    mut online: ResMut<OnlineGame>,
+    settings: Res<Settings>,
+) {
+    *online = OnlineGame::default();
+    lobby.join = None;
+
+    let message = match settings.network.as_ref() {
+        Some(NetworkRole::Host(address)) => {
+            if lobby.host.is_none() {
+                match Host::bind(address.as_str()) {
+                    Ok(host) => lobby.host = Some(host),
+                    Err(error) => warn!("Could not host on {}: {}", address, error),
+                }
+            }
+            match lobby.host.as_ref().map(Host::local_addr) {
+                Some(Ok(address)) => format!("Waiting for an opponent on {}", address),
+                _ => format!("Could not host on {}", address),
+            }
+        }
+        Some(NetworkRole::Join(address)) => {
+            lobby.join = Some(Join::connect(address, CONNECT_TIMEOUT));
+            format!("Joining {}", address)
+        }
+        None => String::from("No network match set up"),
+    };
+    status.0 = format!("{}\n\nPress Backspace to go back", message);
+}
+
+/// Starts the match once the opponent is connected, or goes back to the menu on request.
+fn wait_for_opponent(
+    mut lobby: ResMut<Lobby>,
+    mut status: ResMut<LobbyStatus>,
+    mut online: ResMut<OnlineGame>,
+    mut seed: ResMut<GameSeed>,
+    settings: Res<Settings>,
+    keyboard_input: Res<Input<KeyCode>>,
+    mut app_state: ResMut<State<AppState>>,
+) {
+    if keyboard_input.just_pressed(BACK_KEY) {
+        app_state.set(AppState::MainMenu).unwrap();
+        return;
+    }
+
+    let started = if let Some(host) = lobby.host.as_mut() {
+        let match_seed = settings.seed.unwrap_or_else(|| thread_rng().gen());
+        match host.accept(match_seed, settings.generator) {
+            Ok(connection) => {
+                connection.map(|connection| (connection, match_seed, settings.generator))
+            }
+            Err(error) => {
+                // Hosting goes on for the next opponent.
+                warn!("Turned an opponent away: {}", error);
+                status.0 = format!(
+                    "Turned an opponent away\n{}\n\nStill waiting\nPress Backspace to go back",
+                    error
+                );
+                None
+            }
+        }
+    } else if let Some(join) = lobby.join.as_mut() {
+        match join.poll() {
+            Ok(started) => started,
+            Err(error) => {
+                lobby.join = None;
+                status.0 = format!("Could not join\n{}\n\nPress Backspace to go back", error);
+                None
+            }
+        }
+    } else {
+        None
+    };
+
+    if let Some((connection, match_seed, generator)) = started {
+        seed.0 = match_seed;
+        // The joiner deals with the host's generator, whatever it was started with.
+        let game = Game::new(
+            settings.rules(),
+            generator.create(seed.0),
+            Box::new(Srs),
+            seed.0,
+        );
+        online.online = Some(OnlineMatch::new(game, connection));
+        info!(
+            "Starting network versus with seed {} and {:?} piece generator",
+            seed.0, generator
+        );
+        app_state.set(AppState::Playing).unwrap();
+    }
+}
+
+/// Redraws the lobby screen after its status changed.
+fn show_lobby_status(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    status: Res<LobbyStatus>,
+    overlay_query: Query<(&Overlay, Entity)>,
+) {
+    if !status.is_changed() {
+        return;
+    }
+    overlay_query.for_each(|(_, entity)| {
+        commands.entity(entity).despawn();
+    });
+    spawn_overlay(
+        &mut commands,
+        &asset_server,
+        &format!("NETWORK VERSUS\n\n{}", status.0),
+        BINDINGS_FONT_SIZE,
+        VERSUS_OVERLAY_CORNER,
+    );
+}
+
+/// Redraws both fields from scratch for the match the lobby started.
+fn start_online(mut field_changed_writer: EventWriter<FieldChangedEvent>) {
+    for player in 0..2 {
+        for layer in [FieldLayer::Stack, FieldLayer::Piece] {
+            field_changed_writer.send(FieldChangedEvent { player, layer });
+        }
+    }
+}
+
+fn play_online(
+    mut online: ResMut<OnlineGame>,
+    actions: Res<Input<Action>>,
+    time: Res<Time>,
+    mut field_changed_writer: EventWriter<FieldChangedEvent>,
+    mut app_state: ResMut<State<AppState>>,
+) {
+    let online = &mut *online;
+    let Some(online_match) = online.online.as_mut() else {
+        return;
+    };
+
+    match online_match.step(game_inputs(&actions), time.delta()) {
+        Ok(events) => {
+            for event in events {
+                send_field_changes(&mut field_changed_writer, 0, event);
+            }
+        }
+        // An opponent closing the game after it ended is no failure.
+        Err(_) if online_match.is_over() => {}
+        Err(error) => {
+            warn!("Network match failed: {}", error);
+            online.error = Some(error.to_string());
+            app_state.overwrite_set(AppState::GameOver).unwrap();
+            return;
+        }
+    }
+
+    // The opponent's messages change their board and the local incoming meter.
+    let updates = online_match.opponent().updates;
+    if updates != online.drawn_updates {
+        online.drawn_updates = updates;
+        for player in 0..2 {
+            field_changed_writer.send(FieldChangedEvent {
+                player,
+                layer: FieldLayer::Stack,
+            });
+        }
+    }
+
+    if online_match.is_over() {
+        app_state.overwrite_set(AppState::GameOver).unwrap();
+    }
+}
+
+/// Redraws the parts of each field that changed this frame. Only the opponent's locked
+/// cells are known, so their field has no active piece.
+fn draw_online_fields(
+    mut commands: Commands,
+    online: Res<OnlineGame>,
+    settings: Res<Settings>,
+    sprite_query: Query<(&FieldSprite, Entity)>,
+    mut field_changed_reader: EventReader<FieldChangedEvent>,
+    asset_server: Res<AssetServer>,
+) {
+    let changed: HashSet<(usize, FieldLayer)> = field_changed_reader
+        .iter()
+        .map(|event| (event.player, event.layer))
+        .collect();
+    let Some(online_match) = online.online.as_ref() else {
+        return;
+    };
+    if changed.is_empty() {
+        return;
+    }
+
+    sprite_query.for_each(|(sprite, entity)| {
+        if changed.contains(&(sprite.player, sprite.layer)) {
+            commands.entity(entity).despawn();
+        }
+    });
+
+    let game = online_match.game();
+    let opponent = online_match.opponent();
+    for (player, layer) in changed {
+        let marker = FieldSprite { player, layer };
+        match (player, layer) {
+            (0, FieldLayer::Stack) => draw_stack(&mut commands, &asset_server, game, marker),
+            (0, FieldLayer::Piece) => draw_active_piece(
+                &mut commands,
+                &asset_server,
+                game,
+                marker,
+                settings.show_ghost,
+            ),
+            (_, FieldLayer::Stack) => {
+                draw_cells(&mut commands, &asset_server, &opponent.board, marker);
+                draw_field_text(
+                    &mut commands,
+                    &asset_server,
+                    format!("Pieces {}\nSent {}", opponent.pieces, opponent.garbage_sent),
+                    marker,
+                );
+            }
+            (_, FieldLayer::Piece) => {}
+        }
+    }
+}
+
+fn show_online_results(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    online: Res<OnlineGame>,
+) {
+    let Some(online_match) = online.online.as_ref() else {
+        return;
+    };
+    let title = match (&online.error, online_match.has_won()) {
+        (Some(error), _) => format!("CONNECTION LOST\n{}", error),
+        (None, Some(true)) => String::from("YOU WIN"),
+        (None, _) => String::from("YOU LOSE"),
+    };
+    spawn_overlay(
+        &mut commands,
+        &asset_server,
+        &format!(
+            "{}\n\nTime {}\nSent {} - {}\n\nPress Enter for a new match",
+            title,
+            format_time(online_match.game().elapsed()),
+            online_match.game().statistics().garbage_sent,
+            online_match.opponent().garbage_sent
+        ),
+        OVERLAY_FONT_SIZE,
+        VERSUS_OVERLAY_CORNER,
+    );
+}
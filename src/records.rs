@@ -0,0 +1,138 @@
+use std::collections::BTreeMap;
+use std::time::Duration;
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::settings::{load_config, save_config};
+
+const RECORDS_FILE: &str = "records.ron";
+/// Results kept on each ultra leaderboard.
+const LEADERBOARD_LENGTH: usize = 10;
+
+/// Personal bests of the sprint, ultra and cheese modes, kept in `records.ron` in the config
+/// directory.
+#[derive(Resource, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Records {
+    /// Fastest sprint for each goal, by number of lines.
+    sprints: BTreeMap<i32, SprintRecord>,
+    /// Highest ultra scores for each duration, by seconds, best first.
+    ultras: BTreeMap<u64, Vec<UltraRecord>>,
+    /// Fastest dig through each number of garbage rows.
+    cheeses: BTreeMap<i32, CheeseRecord>,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct SprintRecord {
+    pub time: Duration,
+    pub pieces: u32,
+    /// Time at which each line was cleared, for comparing a run against the record.
+    pub splits: Vec<Duration>,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct UltraRecord {
+    pub score: i32,
+    pub lines: i32,
+    pub pieces: u32,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct CheeseRecord {
+    pub time: Duration,
+    pub pieces: u32,
+}
+
+impl Records {
+    pub fn load() -> Records {
+        load_config(RECORDS_FILE)
+    }
+
+    pub fn save(&self) {
+        save_config(RECORDS_FILE, self);
+    }
+
+    pub fn sprint(&self, lines: i32) -> Option<&SprintRecord> {
+        self.sprints.get(&lines)
+    }
+
+    /// Keeps `record` if it beats the best sprint to `lines`, returning whether it did.
+    pub fn submit_sprint(&mut self, lines: i32, record: SprintRecord) -> bool {
+        if self
+            .sprint(lines)
+            .is_some_and(|best| best.time <= record.time)
+        {
+            return false;
+        }
+        self.sprints.insert(lines, record);
+        true
+    }
+
+    pub fn ultra(&self, duration: Duration) -> &[UltraRecord] {
+        self.ultras
+            .get(&duration.as_secs())
+            .map_or(&[], Vec::as_slice)
+    }
+
+    /// Places `record` on the leaderboard of `duration`, returning its rank counting from 1
+    /// when it makes the board.
+    pub fn submit_ultra(&mut self, duration: Duration, record: UltraRecord) -> Option<usize> {
+        let leaderboard = self.ultras.entry(duration.as_secs()).or_default();
+        let rank = leaderboard
+            .iter()
+            .position(|other| other.score < record.score)
+            .unwrap_or(leaderboard.len());
+        if rank >= LEADERBOARD_LENGTH {
+            return None;
+        }
+        leaderboard.insert(rank, record);
+        leaderboard.truncate(LEADERBOARD_LENGTH);
+        Some(rank + 1)
+    }
+
+    pub fn cheese(&self, rows: i32) -> Option<&CheeseRecord> {
+        self.cheeses.get(&rows)
+    }
+
+    /// Keeps `record` if it beats the best dig through `rows` garbage rows, returning
+    /// whether it did.
+    pub fn submit_cheese(&mut self, rows: i32, record: CheeseRecord) -> bool {
+        if self
+            .cheese(rows)
+            .is_some_and(|best| best.time <= record.time)
+        {
+            return false;
+        }
+        self.cheeses.insert(rows, record);
+        true
+    }
+}
+
+/// What the last finished game earned on the records, worked out once when it ended.
+#[derive(Resource, Default)]
+pub struct Placing {
+    /// The game set a new personal best in a sprint or a cheese.
+    pub is_best: bool,
+    /// Place of the game on the ultra leaderboard, counting from 1.
+    pub rank: Option<usize>,
+}
+
+impl Placing {
+    /// Whether the game changed the records, which then have to be saved.
+    pub fn is_placed(&self) -> bool {
+        self.is_best || self.rank.is_some()
+    }
+}
+
+/// Time at which each line of the current game was cleared.
+#[derive(Resource, Default)]
+pub struct Splits(pub Vec<Duration>);
+
+/// Seconds the current run is ahead (negative) or behind (positive) the record at the last
+/// line cleared.
+pub fn split_delta(splits: &Splits, record: &SprintRecord) -> Option<f32> {
+    let index = splits.0.len().checked_sub(1)?;
+    let record_split = record.splits.get(index)?;
+    Some(splits.0[index].as_secs_f32() - record_split.as_secs_f32())
+}
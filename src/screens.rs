@@ -0,0 +1,515 @@
+use bevy::prelude::*;
+use bevy::text::Text2dBounds;
+use rand::prelude::thread_rng;
+use rand::Rng;
+use tetrominos_core::mode::Mode;
+
+use crate::game_area::*;
+use crate::input::{Action, Bindings, VersusBindings};
+use crate::modes::{mode_name, results_message};
+use crate::records::{Placing, Records, Splits};
+use crate::settings::Preferences;
+use crate::{new_game, ActiveGame, AreaClearedEvent, GameSeed, NewHoldEvent, Settings};
+
+pub const START_KEY: KeyCode = KeyCode::Return;
+pub const BINDINGS_KEY: KeyCode = KeyCode::B;
+pub const GHOST_KEY: KeyCode = KeyCode::G;
+pub const BACK_KEY: KeyCode = KeyCode::Back;
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum AppState {
+    MainMenu,
+    /// Lists the actions and lets the player bind each one to another key, a page for each
+    /// player in versus.
+    Bindings,
+    /// Hosts or joins a network match until the opponent is there.
+    Lobby,
+    Playing,
+    /// Pushed on top of `Playing`, so the board stays as it is until popped.
+    Paused,
+    GameOver,
+}
+
+/// Text and backdrop of a screen drawn over the game area, despawned on leaving the screen.
+#[derive(Component)]
+pub struct Overlay;
+
+/// Action highlighted on the bindings screen, and whether its new key is awaited.
+#[derive(Resource, Default)]
+pub struct BindingsCursor {
+    selected: usize,
+    is_waiting: bool,
+    /// Player whose page is shown in versus.
+    player: usize,
+}
+
+pub fn show_main_menu(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    settings: Res<Settings>,
+) {
+    spawn_overlay(
+        &mut commands,
+        &asset_server,
+        &format!(
+            "TETROMINOS\n{}\n\nPress Enter to start\n\nPress B to change controls\n\
+             Press G to {} the ghost",
+            mode_name(settings.mode),
+            if settings.show_ghost { "hide" } else { "show" }
+        ),
+        OVERLAY_FONT_SIZE,
+        overlay_corner(settings.mode),
+    );
+}
+
+pub fn show_paused(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    bindings: Res<Bindings>,
+    settings: Res<Settings>,
+) {
+    spawn_overlay(
+        &mut commands,
+        &asset_server,
+        &format!(
+            "PAUSED\n\nPress {} to resume",
+            bindings.describe(Action::Pause)
+        ),
+        OVERLAY_FONT_SIZE,
+        overlay_corner(settings.mode),
+    );
+}
+
+pub fn show_game_over(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    game: Res<ActiveGame>,
+    records: Res<Records>,
+    placing: Res<Placing>,
+) {
+    spawn_overlay(
+        &mut commands,
+        &asset_server,
+        &format!(
+            "{}\n\nPress Enter to restart",
+            results_message(&game, &records, &placing)
+        ),
+        OVERLAY_FONT_SIZE,
+        Vec2::ZERO,
+    );
+}
+
+pub fn hide_overlay(mut commands: Commands, overlay_query: Query<(&Overlay, Entity)>) {
+    overlay_query.for_each(|(_, entity)| {
+        commands.entity(entity).despawn();
+    });
+}
+
+/// Starts a game, opens the bindings screen or shows or hides the ghost. The ghost choice
+/// is saved, and the menu redrawn to tell it.
+pub fn menu_on_key(
+    keyboard_input: Res<Input<KeyCode>>,
+    gamepad_buttons: Res<Input<GamepadButton>>,
+    mut actions: ResMut<Input<Action>>,
+    mut settings: ResMut<Settings>,
+    mut app_state: ResMut<State<AppState>>,
+) {
+    if start_requested(&keyboard_input, &gamepad_buttons, &mut actions) {
+        app_state.set(start_state(&settings)).unwrap();
+    } else if keyboard_input.just_pressed(BINDINGS_KEY) {
+        app_state.set(AppState::Bindings).unwrap();
+    } else if keyboard_input.just_pressed(GHOST_KEY) {
+        settings.show_ghost = !settings.show_ghost;
+        Preferences {
+            show_ghost: settings.show_ghost,
+        }
+        .save();
+        app_state.restart().unwrap();
+    }
+}
+
+/// Moves from the game over screen into a new game.
+pub fn restart_on_key(
+    keyboard_input: Res<Input<KeyCode>>,
+    gamepad_buttons: Res<Input<GamepadButton>>,
+    mut actions: ResMut<Input<Action>>,
+    settings: Res<Settings>,
+    mut app_state: ResMut<State<AppState>>,
+) {
+    if start_requested(&keyboard_input, &gamepad_buttons, &mut actions) {
+        app_state.set(start_state(&settings)).unwrap();
+    }
+}
+
+/// Screen a new game starts from: the lobby first when the opponent is over the network.
+fn start_state(settings: &Settings) -> AppState {
+    if settings.network.is_some() {
+        AppState::Lobby
+    } else {
+        AppState::Playing
+    }
+}
+
+/// Whether a new game was asked for. The press also counts as Pause when Start pauses, as
+/// it does by default, so it is used up before it pauses the game it starts.
+fn start_requested(
+    keyboard_input: &Input<KeyCode>,
+    gamepad_buttons: &Input<GamepadButton>,
+    actions: &mut Input<Action>,
+) -> bool {
+    let is_requested = keyboard_input.just_pressed(START_KEY)
+        || gamepad_buttons
+            .get_just_pressed()
+            .any(|button| button.button_type == GamepadButtonType::Start);
+    if is_requested {
+        actions.clear_just_pressed(Action::Pause);
+    }
+    is_requested
+}
+
+/// Pauses the game. The press is used up, as the paused screen runs in the same frame and
+/// would otherwise resume at once.
+pub fn pause_on_action(mut actions: ResMut<Input<Action>>, mut app_state: ResMut<State<AppState>>) {
+    if actions.clear_just_pressed(Action::Pause) {
+        app_state.push(AppState::Paused).unwrap();
+    }
+}
+
+/// Resumes the game, using up the press like `pause_on_action`.
+pub fn resume_on_action(
+    mut actions: ResMut<Input<Action>>,
+    mut app_state: ResMut<State<AppState>>,
+) {
+    if actions.clear_just_pressed(Action::Pause) {
+        app_state.pop().unwrap();
+    }
+}
+
+/// Moves the cursor of the bindings screen, or binds the selected action to the next key
+/// or gamepad button pressed once a rebind was asked for. In local versus the screen edits
+/// the versus bindings, Left and Right turning to the page of the other player. Leaving the
+/// screen saves the bindings.
+pub fn rebind_on_key(
+    keyboard_input: Res<Input<KeyCode>>,
+    gamepad_buttons: Res<Input<GamepadButton>>,
+    mut bindings: ResMut<Bindings>,
+    mut versus_bindings: Option<ResMut<VersusBindings>>,
+    mut cursor: ResMut<BindingsCursor>,
+    mut app_state: ResMut<State<AppState>>,
+) {
+    let actions = listed_actions(versus_bindings.is_some());
+    if cursor.is_waiting {
+        let action = actions[cursor.selected];
+        if let Some(key) = keyboard_input.get_just_pressed().next() {
+            match versus_bindings.as_mut() {
+                Some(versus_bindings) => {
+                    versus_bindings.bind(&mut bindings, cursor.player, action, *key)
+                }
+                None => bindings.bind(action, *key),
+            }
+            cursor.is_waiting = false;
+        } else if let Some(button) = gamepad_buttons.get_just_pressed().next() {
+            match versus_bindings.as_mut() {
+                Some(versus_bindings) => {
+                    versus_bindings.players[cursor.player].bind_button(action, button.button_type)
+                }
+                None => bindings.bind_button(action, button.button_type),
+            }
+            cursor.is_waiting = false;
+        }
+        return;
+    }
+
+    if keyboard_input.just_pressed(KeyCode::Up) {
+        cursor.selected = (cursor.selected + actions.len() - 1) % actions.len();
+    } else if keyboard_input.just_pressed(KeyCode::Down) {
+        cursor.selected = (cursor.selected + 1) % actions.len();
+    } else if versus_bindings.is_some()
+        && keyboard_input.any_just_pressed([KeyCode::Left, KeyCode::Right])
+    {
+        cursor.player = 1 - cursor.player;
+    } else if keyboard_input.just_pressed(START_KEY) {
+        cursor.is_waiting = true;
+    } else if keyboard_input.just_pressed(BACK_KEY) {
+        bindings.save();
+        if let Some(versus_bindings) = versus_bindings {
+            versus_bindings.save();
+        }
+        app_state.set(AppState::MainMenu).unwrap();
+    }
+}
+
+/// Actions listed on the bindings screen. Pause is read from the shared bindings in versus,
+/// so the page of each player leaves it out.
+fn listed_actions(is_versus: bool) -> Vec<Action> {
+    Action::ALL
+        .into_iter()
+        .filter(|action| !is_versus || *action != Action::Pause)
+        .collect()
+}
+
+/// Starts the bindings screen from the first action, which also draws it.
+pub fn reset_bindings_cursor(mut cursor: ResMut<BindingsCursor>) {
+    *cursor = BindingsCursor::default();
+}
+
+/// Redraws the bindings screen after the cursor or a binding changed.
+pub fn show_bindings(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    bindings: Res<Bindings>,
+    versus_bindings: Option<Res<VersusBindings>>,
+    cursor: Res<BindingsCursor>,
+    settings: Res<Settings>,
+    overlay_query: Query<(&Overlay, Entity)>,
+) {
+    if !bindings.is_changed()
+        && !versus_bindings
+            .as_ref()
+            .is_some_and(|versus_bindings| versus_bindings.is_changed())
+        && !cursor.is_changed()
+    {
+        return;
+    }
+    overlay_query.for_each(|(_, entity)| {
+        commands.entity(entity).despawn();
+    });
+
+    let (mut message, shown) = match &versus_bindings {
+        Some(versus_bindings) => (
+            format!("PLAYER {} CONTROLS\n\n", cursor.player + 1),
+            &versus_bindings.players[cursor.player],
+        ),
+        None => (String::from("CONTROLS\n\n"), bindings.as_ref()),
+    };
+    for (index, action) in listed_actions(versus_bindings.is_some())
+        .into_iter()
+        .enumerate()
+    {
+        let keys = if index == cursor.selected && cursor.is_waiting {
+            String::from("...")
+        } else {
+            format!(
+                "{} / {}",
+                shown.describe(action),
+                shown.describe_buttons(action)
+            )
+        };
+        let marker = if index == cursor.selected { "> " } else { "" };
+        message += &format!("{}{}: {}\n", marker, action.name(), keys);
+    }
+    if versus_bindings.is_some() {
+        message += &format!(
+            "{} (both): {}\n\nLeft/Right to switch player",
+            Action::Pause.name(),
+            bindings.describe(Action::Pause)
+        );
+    }
+    message += "\nUp/Down to select\nEnter to rebind\nBackspace to go back";
+
+    spawn_overlay(
+        &mut commands,
+        &asset_server,
+        &message,
+        BINDINGS_FONT_SIZE,
+        overlay_corner(settings.mode),
+    );
+}
+
+/// Replaces the active game with a fresh one and clears whatever the previous game left
+/// on the board and in the hold panel.
+pub fn start_game(
+    mut game: ResMut<ActiveGame>,
+    mut seed: ResMut<GameSeed>,
+    settings: Res<Settings>,
+    mut splits: ResMut<Splits>,
+    mut area_cleared_writer: EventWriter<AreaClearedEvent>,
+    mut new_hold_writer: EventWriter<NewHoldEvent>,
+) {
+    seed.0 = settings.seed.unwrap_or_else(|| thread_rng().gen());
+    game.0 = new_game(&settings, *seed);
+    splits.0.clear();
+    info!(
+        "Starting {} with seed {} and {:?} piece generator",
+        mode_name(settings.mode),
+        seed.0,
+        settings.generator
+    );
+
+    area_cleared_writer.send_default();
+    new_hold_writer.send_default();
+}
+
+/// Corner of the screens drawn over the game, on top of the board or between both boards
+/// in versus.
+fn overlay_corner(mode: Mode) -> Vec2 {
+    match mode {
+        Mode::Versus => VERSUS_OVERLAY_CORNER,
+        _ => Vec2::ZERO,
+    }
+}
+
+pub fn spawn_overlay(
+    commands: &mut Commands,
+    asset_server: &Res<AssetServer>,
+    message: &str,
+    font_size: f32,
+    corner: Vec2,
+) {
+    let font = asset_server.load("fonts/FiraSans-Bold.ttf");
+    let text_style = TextStyle {
+        font,
+        font_size,
+        color: Color::WHITE,
+    };
+
+    commands.spawn((
+        Overlay,
+        SpriteBundle {
+            sprite: Sprite {
+                color: Color::rgba(0., 0., 0., OVERLAY_ALPHA),
+                custom_size: Some(GAME_AREA),
+                ..default()
+            },
+            transform: Transform {
+                translation: calculate_translation(
+                    corner.x,
+                    corner.y,
+                    3.,
+                    GAME_AREA.x,
+                    GAME_AREA.y,
+                ),
+                ..default()
+            },
+            ..default()
+        },
+    ));
+    commands.spawn((
+        Overlay,
+        Text2dBundle {
+            text: Text::from_section(message, text_style).with_alignment(TextAlignment::CENTER),
+            text_2d_bounds: Text2dBounds { size: GAME_AREA },
+            transform: Transform {
+                translation: calculate_translation(
+                    corner.x,
+                    corner.y,
+                    4.,
+                    GAME_AREA.x,
+                    GAME_AREA.y,
+                ),
+                ..default()
+            },
+            ..default()
+        },
+    ));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// An app with nothing but the pause and resume systems, starting in a game.
+    fn pausing_app() -> App {
+        let mut app = App::new();
+        app.init_resource::<Input<Action>>()
+            .add_state(AppState::Playing)
+            .add_system_set(SystemSet::on_update(AppState::Playing).with_system(pause_on_action))
+            .add_system_set(SystemSet::on_update(AppState::Paused).with_system(resume_on_action));
+        app
+    }
+
+    fn press_pause(app: &mut App) {
+        let mut actions = app.world.resource_mut::<Input<Action>>();
+        actions.clear();
+        actions.press(Action::Pause);
+        app.update();
+        let mut actions = app.world.resource_mut::<Input<Action>>();
+        actions.release(Action::Pause);
+        actions.clear();
+    }
+
+    fn state(app: &App) -> AppState {
+        *app.world.resource::<State<AppState>>().current()
+    }
+
+    #[test]
+    fn pause_press_pauses_once() {
+        let mut app = pausing_app();
+        press_pause(&mut app);
+        assert_eq!(state(&app), AppState::Paused);
+
+        app.update();
+        assert_eq!(state(&app), AppState::Paused);
+    }
+
+    #[test]
+    fn second_pause_press_resumes() {
+        let mut app = pausing_app();
+        press_pause(&mut app);
+        press_pause(&mut app);
+        assert_eq!(state(&app), AppState::Playing);
+    }
+
+    #[test]
+    fn gamepad_start_starts_without_pausing() {
+        let mut app = App::new();
+        app.init_resource::<Input<Action>>()
+            .init_resource::<Input<KeyCode>>()
+            .init_resource::<Input<GamepadButton>>()
+            .insert_resource(Settings::default())
+            .add_state(AppState::MainMenu)
+            .add_system_set(SystemSet::on_update(AppState::MainMenu).with_system(menu_on_key))
+            .add_system_set(SystemSet::on_update(AppState::Playing).with_system(pause_on_action));
+
+        // Start is bound to Pause, so reading the actions presses both.
+        app.world
+            .resource_mut::<Input<GamepadButton>>()
+            .press(GamepadButton::new(
+                Gamepad::new(0),
+                GamepadButtonType::Start,
+            ));
+        app.world
+            .resource_mut::<Input<Action>>()
+            .press(Action::Pause);
+        app.update();
+        assert_eq!(state(&app), AppState::Playing);
+    }
+
+    fn press_key(app: &mut App, key: KeyCode) {
+        app.world.resource_mut::<Input<KeyCode>>().press(key);
+        app.update();
+        let mut keyboard_input = app.world.resource_mut::<Input<KeyCode>>();
+        keyboard_input.release(key);
+        keyboard_input.clear();
+    }
+
+    #[test]
+    fn versus_rebinds_the_player_whose_page_is_shown() {
+        let mut app = App::new();
+        app.init_resource::<Input<KeyCode>>()
+            .init_resource::<Input<GamepadButton>>()
+            .init_resource::<Bindings>()
+            .init_resource::<VersusBindings>()
+            .init_resource::<BindingsCursor>()
+            .add_state(AppState::Bindings)
+            .add_system_set(SystemSet::on_update(AppState::Bindings).with_system(rebind_on_key));
+
+        press_key(&mut app, KeyCode::Right);
+        press_key(&mut app, START_KEY);
+        press_key(&mut app, KeyCode::J);
+
+        let versus_bindings = app.world.resource::<VersusBindings>();
+        assert_eq!(
+            versus_bindings.players[1].keys(Action::MoveLeft),
+            &[KeyCode::J]
+        );
+        assert_eq!(
+            versus_bindings.players[0].keys(Action::MoveLeft),
+            &[KeyCode::A]
+        );
+        assert_eq!(
+            app.world.resource::<Bindings>().keys(Action::MoveLeft),
+            &[KeyCode::Left]
+        );
+    }
+}
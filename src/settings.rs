@@ -0,0 +1,367 @@
+use std::fs;
+use std::path::PathBuf;
+use std::str::FromStr;
+use std::time::Duration;
+
+use bevy::prelude::*;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use tetrominos_core::game::Rules;
+use tetrominos_core::generator::GeneratorKind;
+use tetrominos_core::gravity::{GravityTable, MAX_GRAVITY};
+use tetrominos_core::mode::Mode;
+
+const PREFERENCES_FILE: &str = "preferences.ron";
+
+pub const USAGE: &str = "\
+Usage: rust-tetrominos [options]
+
+  --mode <marathon|sprint|ultra|cheese|survival|versus>
+  --lines <number>               lines to clear in sprint
+  --seconds <number>             length of ultra
+  --rows <1..18>                 garbage rows to dig out in cheese
+  --host <address>               host versus over the network
+  --join <address>               join versus over the network
+  --generator <bag|random|nes|tgm>
+  --seed <number>
+  --gravity <rows per frame,...> speed of each level from the first
+  --lines-per-level <number>
+  --no-ghost
+  --lock-delay <milliseconds>
+  --lock-resets <number>
+  --das <milliseconds>
+  --arr <milliseconds>
+  --soft-drop-factor <number>
+  --soft-drop-points <number>
+  --hard-drop-points <number>
+  --entry-delay <milliseconds>
+  --queue <1..6>
+  --deadzone <0..1>";
+
+#[derive(Resource)]
+pub struct Settings {
+    pub mode: Mode,
+    pub generator: GeneratorKind,
+    pub seed: Option<u64>,
+    pub gravity: GravityTable,
+    pub lines_per_level: i32,
+    pub show_ghost: bool,
+    pub lock_delay: Duration,
+    pub max_lock_resets: u32,
+    pub das: Duration,
+    pub arr: Duration,
+    pub soft_drop_factor: f32,
+    pub soft_drop_points: i32,
+    pub hard_drop_points: i32,
+    pub entry_delay: Duration,
+    pub queue_length: usize,
+    /// How far the left stick has to tilt, from 0 to 1, before it counts as a press.
+    pub stick_deadzone: f32,
+    /// Whether versus is played against someone over the network instead of on this
+    /// keyboard.
+    pub network: Option<NetworkRole>,
+}
+
+/// Choices made in game instead of on the command line, kept between runs.
+#[derive(Serialize, Deserialize)]
+#[serde(default)]
+pub struct Preferences {
+    pub show_ghost: bool,
+}
+
+impl Default for Preferences {
+    fn default() -> Self {
+        Preferences { show_ghost: true }
+    }
+}
+
+impl Preferences {
+    pub fn load() -> Preferences {
+        load_config(PREFERENCES_FILE)
+    }
+
+    pub fn save(&self) {
+        save_config(PREFERENCES_FILE, self);
+    }
+}
+
+/// Side taken in a network match, with the address to listen on or connect to.
+#[derive(Clone, Debug)]
+pub enum NetworkRole {
+    Host(String),
+    Join(String),
+}
+
+impl Default for Settings {
+    /// Rules start from the defaults of the core crate, so that both agree.
+    fn default() -> Self {
+        let rules = Rules::default();
+        Settings {
+            mode: rules.mode,
+            generator: GeneratorKind::SevenBag,
+            seed: None,
+            gravity: rules.gravity,
+            lines_per_level: rules.lines_per_level,
+            show_ghost: true,
+            lock_delay: rules.lock_delay,
+            max_lock_resets: rules.max_lock_resets,
+            das: rules.das,
+            arr: rules.arr,
+            soft_drop_factor: rules.soft_drop_factor,
+            soft_drop_points: rules.soft_drop_points,
+            hard_drop_points: rules.hard_drop_points,
+            entry_delay: rules.entry_delay,
+            queue_length: rules.queue_length,
+            stick_deadzone: 0.5,
+            network: None,
+        }
+    }
+}
+
+impl Settings {
+    pub fn rules(&self) -> Rules {
+        Rules {
+            mode: self.mode,
+            gravity: self.gravity.clone(),
+            lines_per_level: self.lines_per_level,
+            lock_delay: self.lock_delay,
+            max_lock_resets: self.max_lock_resets,
+            das: self.das,
+            arr: self.arr,
+            soft_drop_factor: self.soft_drop_factor,
+            soft_drop_points: self.soft_drop_points,
+            hard_drop_points: self.hard_drop_points,
+            entry_delay: self.entry_delay,
+            queue_length: self.queue_length,
+        }
+    }
+
+    /// Reads the options listed in [`USAGE`] from the command line, on top of the saved
+    /// preferences. `--no-ghost` hides the ghost for this run without saving it.
+    pub fn from_args() -> Result<Settings, String> {
+        Settings::parse_args(std::env::args().skip(1), Preferences::load())
+    }
+
+    /// Reads every option first and builds the mode afterwards, so that the goal of a mode
+    /// can be given before or after `--mode`.
+    fn parse_args(
+        args: impl IntoIterator<Item = String>,
+        preferences: Preferences,
+    ) -> Result<Settings, String> {
+        let mut settings = Settings {
+            show_ghost: preferences.show_ghost,
+            ..Settings::default()
+        };
+        let mut mode = None;
+        let mut lines = None;
+        let mut seconds = None;
+        let mut rows = None;
+
+        let mut args = args.into_iter();
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--mode" => {
+                    let name: String = next_value(&mut args, &arg, |_| true)?;
+                    mode = Some(Mode::parse(&name).ok_or(format!("Unknown mode: {}", name))?);
+                }
+                "--lines" => lines = Some(next_value(&mut args, &arg, |lines| *lines > 0)?),
+                "--seconds" => seconds = Some(next_value(&mut args, &arg, |seconds| *seconds > 0)?),
+                "--rows" => {
+                    rows = Some(next_value(&mut args, &arg, |rows| (1..=18).contains(rows))?)
+                }
+                "--host" => {
+                    let address = next_value(&mut args, &arg, |_| true)?;
+                    settings.network = Some(NetworkRole::Host(address));
+                }
+                "--join" => {
+                    let address = next_value(&mut args, &arg, |_| true)?;
+                    settings.network = Some(NetworkRole::Join(address));
+                }
+                "--generator" => {
+                    let name: String = next_value(&mut args, &arg, |_| true)?;
+                    settings.generator = GeneratorKind::parse(&name)
+                        .ok_or(format!("Unknown generator: {}", name))?;
+                }
+                "--seed" => settings.seed = Some(next_value(&mut args, &arg, |_| true)?),
+                "--gravity" => {
+                    let value: String = next_value(&mut args, &arg, |_| true)?;
+                    let rows_per_frame = value
+                        .split(',')
+                        .map(|speed| {
+                            speed
+                                .trim()
+                                .parse()
+                                .ok()
+                                .filter(|speed| *speed > 0. && *speed <= MAX_GRAVITY)
+                        })
+                        .collect::<Option<Vec<f32>>>()
+                        .ok_or(format!("Wrong value for {}: {}", arg, value))?;
+                    settings.gravity = GravityTable::new(rows_per_frame);
+                }
+                "--lines-per-level" => {
+                    settings.lines_per_level = next_value(&mut args, &arg, |lines| *lines > 0)?
+                }
+                "--no-ghost" => settings.show_ghost = false,
+                "--lock-delay" => settings.lock_delay = next_millis(&mut args, &arg)?,
+                "--lock-resets" => {
+                    settings.max_lock_resets = next_value(&mut args, &arg, |_| true)?
+                }
+                "--das" => settings.das = next_millis(&mut args, &arg)?,
+                "--arr" => settings.arr = next_millis(&mut args, &arg)?,
+                "--soft-drop-factor" => {
+                    settings.soft_drop_factor = next_value(&mut args, &arg, |factor| *factor >= 1.)?
+                }
+                "--soft-drop-points" => {
+                    settings.soft_drop_points = next_value(&mut args, &arg, |points| *points >= 0)?
+                }
+                "--hard-drop-points" => {
+                    settings.hard_drop_points = next_value(&mut args, &arg, |points| *points >= 0)?
+                }
+                "--entry-delay" => settings.entry_delay = next_millis(&mut args, &arg)?,
+                "--queue" => {
+                    settings.queue_length =
+                        next_value(&mut args, &arg, |length| (1..=6).contains(length))?
+                }
+                "--deadzone" => {
+                    settings.stick_deadzone =
+                        next_value(&mut args, &arg, |deadzone| (0.0..1.0).contains(deadzone))?
+                }
+                _ => return Err(format!("Unknown argument: {}", arg)),
+            }
+        }
+
+        settings.mode = match (&settings.network, mode) {
+            (Some(_), None | Some(Mode::Versus)) => Mode::Versus,
+            (Some(_), Some(_)) => {
+                return Err("--host and --join only play --mode versus".to_string())
+            }
+            (None, mode) => mode.unwrap_or(Mode::Marathon),
+        };
+        if let Some(value) = lines {
+            let Mode::Sprint { lines } = &mut settings.mode else {
+                return Err("--lines needs --mode sprint".to_string());
+            };
+            *lines = value;
+        }
+        if let Some(value) = seconds {
+            let Mode::Ultra { duration } = &mut settings.mode else {
+                return Err("--seconds needs --mode ultra".to_string());
+            };
+            *duration = Duration::from_secs(value);
+        }
+        if let Some(value) = rows {
+            let Mode::Cheese { rows } = &mut settings.mode else {
+                return Err("--rows needs --mode cheese".to_string());
+            };
+            *rows = value;
+        }
+        Ok(settings)
+    }
+}
+
+/// Takes the value following `flag`, failing when it is missing, does not parse or is not
+/// accepted by `is_valid`.
+fn next_value<T: FromStr>(
+    args: &mut impl Iterator<Item = String>,
+    flag: &str,
+    is_valid: impl Fn(&T) -> bool,
+) -> Result<T, String> {
+    let value = args
+        .next()
+        .ok_or_else(|| format!("{} needs a value", flag))?;
+    value
+        .parse()
+        .ok()
+        .filter(|parsed| is_valid(parsed))
+        .ok_or_else(|| format!("Wrong value for {}: {}", flag, value))
+}
+
+fn next_millis(args: &mut impl Iterator<Item = String>, flag: &str) -> Result<Duration, String> {
+    next_value(args, flag, |_| true).map(Duration::from_millis)
+}
+
+/// Location of `file` in the per-user config directory, if the platform has one.
+fn config_path(file: &str) -> Option<PathBuf> {
+    dirs::config_dir().map(|dir| dir.join("rust-tetrominos").join(file))
+}
+
+/// Reads `file` from the config directory, falling back to the default for a missing or
+/// unreadable file.
+pub fn load_config<T: DeserializeOwned + Default>(file: &str) -> T {
+    let Some(path) = config_path(file) else {
+        return T::default();
+    };
+    let Ok(content) = fs::read_to_string(&path) else {
+        return T::default();
+    };
+
+    ron::from_str(&content).unwrap_or_else(|error| {
+        warn!("Ignoring {}: {}", path.display(), error);
+        T::default()
+    })
+}
+
+pub fn save_config<T: Serialize>(file: &str, value: &T) {
+    let Some(path) = config_path(file) else {
+        return;
+    };
+    let content = ron::ser::to_string_pretty(value, ron::ser::PrettyConfig::default())
+        .expect("Config files are always serializable");
+    if let Err(error) = path
+        .parent()
+        .map_or(Ok(()), fs::create_dir_all)
+        .and_then(|_| fs::write(&path, content))
+    {
+        warn!("Could not save {}: {}", path.display(), error);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &[&str]) -> Result<Settings, String> {
+        Settings::parse_args(
+            args.iter().map(|arg| arg.to_string()),
+            Preferences::default(),
+        )
+    }
+
+    #[test]
+    fn mode_goals_apply_before_or_after_their_mode() {
+        for args in [
+            ["--lines", "20", "--mode", "sprint"],
+            ["--mode", "sprint", "--lines", "20"],
+        ] {
+            assert_eq!(
+                parse(&args).map(|settings| settings.mode),
+                Ok(Mode::Sprint { lines: 20 })
+            );
+        }
+    }
+
+    #[test]
+    fn goal_of_another_mode_is_an_error() {
+        assert!(parse(&["--mode", "ultra", "--lines", "20"]).is_err());
+        assert!(parse(&["--rows", "5"]).is_err());
+    }
+
+    #[test]
+    fn network_play_is_versus_only() {
+        assert_eq!(
+            parse(&["--host", "0.0.0.0:7878"]).map(|settings| settings.mode),
+            Ok(Mode::Versus)
+        );
+        assert!(parse(&["--mode", "versus", "--join", "localhost:7878"]).is_ok());
+        assert!(parse(&["--join", "localhost:7878", "--mode", "sprint"]).is_err());
+    }
+
+    #[test]
+    fn bad_or_missing_values_are_errors() {
+        assert!(parse(&["--mode", "sprint", "--lines", "abc"]).is_err());
+        assert!(parse(&["--mode", "cheese", "--rows", "19"]).is_err());
+        assert!(parse(&["--das"]).is_err());
+        assert!(parse(&["--mode", "zen"]).is_err());
+        assert!(parse(&["--speed", "5"]).is_err());
+    }
+}
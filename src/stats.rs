@@ -0,0 +1,142 @@
+use std::time::Duration;
+
+use bevy::prelude::*;
+use tetrominos_core::piece::Piece;
+
+use crate::game_area::*;
+use crate::piece::PieceImage;
+use crate::ActiveGame;
+
+/// Text of the stats panel, by which part of the panel it shows.
+#[derive(Component)]
+pub enum StatsText {
+    Summary,
+    PieceCount(Piece),
+    Clears,
+}
+
+/// Formats a duration as minutes, seconds and tenths, such as `1:05.3`.
+pub fn format_time(duration: Duration) -> String {
+    let tenths = duration.as_millis() / 100;
+    format!("{}:{:02}.{}", tenths / 600, tenths / 10 % 60, tenths % 10)
+}
+
+/// Formats a duration down to the millisecond, such as `1:05.321`, for times raced against.
+pub fn format_precise_time(duration: Duration) -> String {
+    let millis = duration.as_millis();
+    format!(
+        "{}:{:02}.{:03}",
+        millis / 60_000,
+        millis / 1000 % 60,
+        millis % 1000
+    )
+}
+
+/// Lays out the stats panel: a summary at the top, a row per piece with its icon and count,
+/// and the number of clears of each size at the bottom.
+pub fn spawn_stats_panel(
+    commands: &mut Commands,
+    asset_server: &Res<AssetServer>,
+    game: &ActiveGame,
+) {
+    let font = asset_server.load("fonts/FiraSans-Bold.ttf");
+    let text_style = TextStyle {
+        font,
+        font_size: STATS_FONT_SIZE,
+        color: Color::WHITE,
+    };
+    let text_at = |stat: StatsText, x: f32, y: f32, alignment: TextAlignment| {
+        (
+            stat,
+            Text2dBundle {
+                text: Text::from_section("", text_style.clone()).with_alignment(alignment),
+                transform: Transform {
+                    translation: calculate_translation(x, y, 2., 0., 0.),
+                    ..default()
+                },
+                ..default()
+            },
+        )
+    };
+
+    commands.spawn(text_at(
+        StatsText::Summary,
+        STATS_CORNER.x,
+        STATS_CORNER.y,
+        TextAlignment::TOP_LEFT,
+    ));
+
+    for (row, piece) in Piece::ALL.into_iter().enumerate() {
+        let row_y = STATS_CORNER.y + (STATS_SUMMARY_LINES + row as f32) * STATS_LINE_HEIGHT;
+        let tiles = game.0.rotation().get_tiles(&piece, 0, 0, 0);
+        let min_x = tiles.iter().map(|tile| tile.0).min().unwrap_or_default();
+        let min_y = tiles.iter().map(|tile| tile.1).min().unwrap_or_default();
+        for tile in tiles {
+            commands.spawn(SpriteBundle {
+                texture: piece.get_image(asset_server),
+                transform: panel_tile_translation(
+                    Vec2::new(STATS_CORNER.x - MARGIN, row_y - MARGIN),
+                    STATS_TILE_SIZE,
+                    tile,
+                    -min_x as f32 * STATS_TILE_SIZE,
+                    -min_y as f32 * STATS_TILE_SIZE,
+                ),
+                ..default()
+            });
+        }
+
+        commands.spawn(text_at(
+            StatsText::PieceCount(piece),
+            STATS_CORNER.x + STATS_ICON_WIDTH,
+            row_y + STATS_TILE_SIZE,
+            TextAlignment::CENTER_LEFT,
+        ));
+    }
+
+    commands.spawn(text_at(
+        StatsText::Clears,
+        STATS_CORNER.x,
+        STATS_CORNER.y + (STATS_SUMMARY_LINES + Piece::ALL.len() as f32 + 1.) * STATS_LINE_HEIGHT,
+        TextAlignment::TOP_LEFT,
+    ));
+}
+
+pub fn update_stats(game: Res<ActiveGame>, mut text_query: Query<(&StatsText, &mut Text)>) {
+    let state = game.0.state();
+    let statistics = game.0.statistics();
+    let elapsed = game.0.elapsed();
+
+    for (stat, mut text) in text_query.iter_mut() {
+        text.sections[0].value = match stat {
+            StatsText::Summary => format!(
+                "Lines {}\nLevel {}\nTime {}\nPieces {}\nPPS {:.2}",
+                state.lines,
+                state.level,
+                format_time(elapsed),
+                statistics.pieces,
+                statistics.pieces_per_second(elapsed)
+            ),
+            StatsText::PieceCount(piece) => statistics.pieces_of(*piece).to_string(),
+            StatsText::Clears => format!(
+                "Singles {}\nDoubles {}\nTriples {}\nTetrises {}",
+                statistics.clears_of(1),
+                statistics.clears_of(2),
+                statistics.clears_of(3),
+                statistics.clears_of(4)
+            ),
+        };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn times_show_minutes_seconds_and_fractions() {
+        let time = Duration::from_millis(65_321);
+        assert_eq!(format_time(time), "1:05.3");
+        assert_eq!(format_precise_time(time), "1:05.321");
+        assert_eq!(format_precise_time(Duration::from_millis(7)), "0:00.007");
+    }
+}
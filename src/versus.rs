@@ -0,0 +1,440 @@
+use std::collections::HashSet;
+
+use bevy::input::InputSystem;
+use bevy::prelude::*;
+use bevy::sprite::MaterialMesh2dBundle;
+use bevy::text::Text2dBounds;
+use rand::prelude::thread_rng;
+use rand::Rng;
+use tetrominos_core::board::Board;
+use tetrominos_core::game::{Game, GameEvent};
+use tetrominos_core::versus::Versus;
+
+use crate::game_area::*;
+use crate::input::{
+    game_inputs, read_versus_actions, track_gamepads, ConnectedGamepads, VersusActions,
+    VersusBindings,
+};
+use crate::piece::PieceImage;
+use crate::screens::{pause_on_action, spawn_overlay, AppState};
+use crate::stats::format_time;
+use crate::{new_game, place_panel_piece, GameSeed, Settings};
+
+/// Two players side by side in one window, each with their own board, keys and gamepad.
+pub struct VersusPlugin;
+
+impl Plugin for VersusPlugin {
+    fn build(&self, app: &mut App) {
+        let versus = new_versus(
+            app.world.resource::<Settings>(),
+            *app.world.resource::<GameSeed>(),
+        );
+        app.insert_resource(VersusMatch(versus))
+            .insert_resource(VersusBindings::load())
+            .init_resource::<VersusActions>()
+            .init_resource::<ConnectedGamepads>()
+            .add_event::<FieldChangedEvent>()
+            .add_startup_system(setup_versus)
+            .add_system_to_stage(CoreStage::PreUpdate, track_gamepads.after(InputSystem))
+            .add_system_to_stage(
+                CoreStage::PreUpdate,
+                read_versus_actions.after(track_gamepads),
+            )
+            .add_system_set(SystemSet::on_enter(AppState::Playing).with_system(start_versus))
+            .add_system_set(
+                SystemSet::on_update(AppState::Playing)
+                    .with_system(play_versus)
+                    .with_system(pause_on_action.before(play_versus))
+                    .with_system(draw_fields.after(play_versus)),
+            )
+            .add_system_set(
+                SystemSet::on_enter(AppState::GameOver).with_system(show_versus_results),
+            );
+    }
+}
+
+#[derive(Resource)]
+struct VersusMatch(Versus);
+
+/// Part of a player's field that is redrawn together.
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+pub enum FieldLayer {
+    /// Locked cells, incoming garbage, next queue, hold and score.
+    Stack,
+    /// Active piece and its ghost.
+    Piece,
+}
+
+#[derive(Component, Clone, Copy)]
+pub struct FieldSprite {
+    pub player: usize,
+    pub layer: FieldLayer,
+}
+
+pub struct FieldChangedEvent {
+    pub player: usize,
+    pub layer: FieldLayer,
+}
+
+/// Both players get the same pieces and the same garbage holes.
+fn new_versus(settings: &Settings, seed: GameSeed) -> Versus {
+    Versus::new([new_game(settings, seed), new_game(settings, seed)])
+}
+
+fn setup_versus(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+) {
+    spawn_versus_background(&mut commands, &mut meshes, &mut materials);
+    for player in 0..2 {
+        spawn_field(&mut commands, &mut meshes, &mut materials, player, true);
+    }
+}
+
+pub fn spawn_versus_background(
+    commands: &mut Commands,
+    meshes: &mut Assets<Mesh>,
+    materials: &mut Assets<ColorMaterial>,
+) {
+    commands.spawn(Camera2dBundle::default());
+    commands.spawn(MaterialMesh2dBundle {
+        mesh: meshes.add(Mesh::from(shape::Quad::default())).into(),
+        transform: Transform::default().with_scale(Vec3::from((VERSUS_BOUNDS, 0.))),
+        material: materials.add(ColorMaterial::from(Color::rgb_u8(51, 53, 66))),
+        ..default()
+    });
+}
+
+/// Lays out the empty board of `player`, along with their garbage meter, next queue and
+/// hold when `has_panels`.
+pub fn spawn_field(
+    commands: &mut Commands,
+    meshes: &mut Assets<Mesh>,
+    materials: &mut Assets<ColorMaterial>,
+    player: usize,
+    has_panels: bool,
+) {
+    let mut areas = vec![(versus_board_corner(player), GAME_AREA)];
+    if has_panels {
+        areas.extend([
+            (
+                versus_field_corner(player),
+                Vec2::new(METER_WIDTH, GAME_AREA.y),
+            ),
+            (versus_queue_corner(player), queue_area(VERSUS_QUEUE_LENGTH)),
+            (versus_hold_corner(player), SLOT_AREA),
+        ]);
+    }
+    for (corner, area) in areas {
+        commands.spawn(MaterialMesh2dBundle {
+            mesh: meshes.add(Mesh::from(shape::Quad::default())).into(),
+            transform: calculate_transform(corner.x, corner.y, 0.1, area.x, area.y),
+            material: materials.add(ColorMaterial::from(Color::BLACK)),
+            ..default()
+        });
+    }
+}
+
+/// Starts a new match and redraws both fields from scratch.
+fn start_versus(
+    mut versus: ResMut<VersusMatch>,
+    mut seed: ResMut<GameSeed>,
+    settings: Res<Settings>,
+    mut field_changed_writer: EventWriter<FieldChangedEvent>,
+) {
+    seed.0 = settings.seed.unwrap_or_else(|| thread_rng().gen());
+    versus.0 = new_versus(&settings, *seed);
+    info!("Starting versus with seed {}", seed.0);
+
+    for player in 0..2 {
+        for layer in [FieldLayer::Stack, FieldLayer::Piece] {
+            field_changed_writer.send(FieldChangedEvent { player, layer });
+        }
+    }
+}
+
+fn play_versus(
+    mut versus: ResMut<VersusMatch>,
+    actions: Res<VersusActions>,
+    time: Res<Time>,
+    mut field_changed_writer: EventWriter<FieldChangedEvent>,
+    mut app_state: ResMut<State<AppState>>,
+) {
+    let inputs = [game_inputs(&actions.0[0]), game_inputs(&actions.0[1])];
+    let events = versus.0.step(inputs, time.delta());
+
+    for (player, events) in events.into_iter().enumerate() {
+        for event in events {
+            send_field_changes(&mut field_changed_writer, player, event);
+        }
+    }
+
+    if versus.0.is_over() {
+        app_state.overwrite_set(AppState::GameOver).unwrap();
+    }
+}
+
+/// Marks the parts of the fields that `event` of `player` changed.
+pub fn send_field_changes(
+    field_changed_writer: &mut EventWriter<FieldChangedEvent>,
+    player: usize,
+    event: GameEvent,
+) {
+    let (player, layers): (usize, &[FieldLayer]) = match event {
+        GameEvent::NewPosition => (player, &[FieldLayer::Piece]),
+        GameEvent::NewPiece
+        | GameEvent::Hold
+        | GameEvent::Locked(_)
+        | GameEvent::LinesCleared(_) => (player, &[FieldLayer::Stack]),
+        GameEvent::GarbageAdded { .. } => (player, &[FieldLayer::Stack, FieldLayer::Piece]),
+        // The opponent's meter shows the incoming garbage.
+        GameEvent::Attack(_) => (1 - player, &[FieldLayer::Stack]),
+        GameEvent::GameOver(ending) => {
+            info!("Player {} is out: {:?}", player + 1, ending);
+            return;
+        }
+        GameEvent::TSpin(..)
+        | GameEvent::BackToBack(_)
+        | GameEvent::Combo(_)
+        | GameEvent::PerfectClear
+        | GameEvent::LevelUp(_) => return,
+    };
+    for layer in layers {
+        field_changed_writer.send(FieldChangedEvent {
+            player,
+            layer: *layer,
+        });
+    }
+}
+
+/// Redraws the parts of each field that changed this frame.
+fn draw_fields(
+    mut commands: Commands,
+    versus: Res<VersusMatch>,
+    settings: Res<Settings>,
+    sprite_query: Query<(&FieldSprite, Entity)>,
+    mut field_changed_reader: EventReader<FieldChangedEvent>,
+    asset_server: Res<AssetServer>,
+) {
+    let changed: HashSet<(usize, FieldLayer)> = field_changed_reader
+        .iter()
+        .map(|event| (event.player, event.layer))
+        .collect();
+    if changed.is_empty() {
+        return;
+    }
+
+    sprite_query.for_each(|(sprite, entity)| {
+        if changed.contains(&(sprite.player, sprite.layer)) {
+            commands.entity(entity).despawn();
+        }
+    });
+
+    for (player, layer) in changed {
+        let game = versus.0.game(player);
+        let marker = FieldSprite { player, layer };
+        match layer {
+            FieldLayer::Stack => draw_stack(&mut commands, &asset_server, game, marker),
+            FieldLayer::Piece => draw_active_piece(
+                &mut commands,
+                &asset_server,
+                game,
+                marker,
+                settings.show_ghost,
+            ),
+        }
+    }
+}
+
+pub fn draw_stack(
+    commands: &mut Commands,
+    asset_server: &Res<AssetServer>,
+    game: &Game,
+    marker: FieldSprite,
+) {
+    let player = marker.player;
+    draw_cells(commands, asset_server, game.board(), marker);
+
+    let incoming = game.incoming_garbage().min(VERTICAL_TILES as i32) as f32 * TILE_SIZE;
+    if incoming > 0. {
+        let meter = versus_field_corner(player);
+        commands.spawn((
+            marker,
+            SpriteBundle {
+                sprite: Sprite {
+                    color: Color::RED,
+                    custom_size: Some(Vec2::new(METER_WIDTH, incoming)),
+                    ..default()
+                },
+                transform: Transform {
+                    translation: calculate_translation(
+                        meter.x,
+                        meter.y + GAME_AREA.y - incoming,
+                        1.,
+                        METER_WIDTH,
+                        incoming,
+                    ),
+                    ..default()
+                },
+                ..default()
+            },
+        ));
+    }
+
+    for (index, piece) in game.queue().iter().take(VERSUS_QUEUE_LENGTH).enumerate() {
+        let (corner, tile_size) = queue_slot(versus_queue_corner(player), index);
+        place_panel_piece(
+            commands,
+            asset_server,
+            game.rotation(),
+            piece,
+            corner,
+            tile_size,
+            marker,
+        );
+    }
+    if let Some(piece) = game.hold() {
+        place_panel_piece(
+            commands,
+            asset_server,
+            game.rotation(),
+            &piece,
+            versus_hold_corner(player),
+            TILE_SIZE,
+            marker,
+        );
+    }
+
+    draw_field_text(
+        commands,
+        asset_server,
+        format!(
+            "{}\nSent {}",
+            game.state().score,
+            game.statistics().garbage_sent
+        ),
+        marker,
+    );
+}
+
+/// Locked cells of `board`, drawn on the board of the player `marker` belongs to.
+pub fn draw_cells(
+    commands: &mut Commands,
+    asset_server: &Res<AssetServer>,
+    board: &Board,
+    marker: FieldSprite,
+) {
+    let corner = versus_board_corner(marker.player);
+    for (x, y, block) in board.cells() {
+        commands.spawn((
+            marker,
+            SpriteBundle {
+                sprite: Sprite {
+                    color: block.tint(),
+                    ..default()
+                },
+                texture: block.get_image(asset_server),
+                transform: board_tile_transform(corner, (x, y)),
+                ..default()
+            },
+        ));
+    }
+}
+
+/// Text under the side panel of the player `marker` belongs to.
+pub fn draw_field_text(
+    commands: &mut Commands,
+    asset_server: &Res<AssetServer>,
+    value: String,
+    marker: FieldSprite,
+) {
+    let score = versus_score_corner(marker.player);
+    commands.spawn((
+        marker,
+        Text2dBundle {
+            text: Text::from_section(
+                value,
+                TextStyle {
+                    font: asset_server.load("fonts/FiraSans-Bold.ttf"),
+                    font_size: MODE_FONT_SIZE,
+                    color: Color::WHITE,
+                },
+            )
+            .with_alignment(TextAlignment::CENTER),
+            text_2d_bounds: Text2dBounds { size: MODE_AREA },
+            transform: Transform {
+                translation: calculate_translation(score.x, score.y, 2., MODE_AREA.x, MODE_AREA.y),
+                ..default()
+            },
+            ..default()
+        },
+    ));
+}
+
+pub fn draw_active_piece(
+    commands: &mut Commands,
+    asset_server: &Res<AssetServer>,
+    game: &Game,
+    marker: FieldSprite,
+    show_ghost: bool,
+) {
+    let Some(position) = game.position() else {
+        return;
+    };
+    let board = versus_board_corner(marker.player);
+    let ghost_tiles = if show_ghost {
+        game.ghost_tiles()
+    } else {
+        Vec::new()
+    };
+    for (tile, alpha, z) in game
+        .piece_tiles()
+        .into_iter()
+        .map(|tile| (tile, 1., 1.))
+        .chain(ghost_tiles.into_iter().map(|tile| (tile, GHOST_ALPHA, 0.5)))
+    {
+        let mut transform = board_tile_transform(board, tile);
+        transform.translation.z = z;
+        commands.spawn((
+            marker,
+            SpriteBundle {
+                sprite: Sprite {
+                    color: Color::rgba(1., 1., 1., alpha),
+                    ..default()
+                },
+                texture: position.piece.get_image(asset_server),
+                visibility: Visibility {
+                    is_visible: tile.1 >= 0,
+                },
+                transform,
+                ..default()
+            },
+        ));
+    }
+}
+
+fn show_versus_results(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    versus: Res<VersusMatch>,
+) {
+    let title = match versus.0.winner() {
+        Some(player) => format!("PLAYER {} WINS", player + 1),
+        None => String::from("DRAW"),
+    };
+    let sent = |player| versus.0.game(player).statistics().garbage_sent;
+    spawn_overlay(
+        &mut commands,
+        &asset_server,
+        &format!(
+            "{}\n\nTime {}\nSent {} - {}\n\nPress Enter to restart",
+            title,
+            format_time(versus.0.game(0).elapsed()),
+            sent(0),
+            sent(1)
+        ),
+        OVERLAY_FONT_SIZE,
+        VERSUS_OVERLAY_CORNER,
+    );
+}
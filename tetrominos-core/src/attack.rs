@@ -0,0 +1,40 @@
+use crate::game::Spin;
+
+/// Extra rows sent by each step of a combo, the index being how many clears in a row
+/// came before.
+const COMBO_ATTACK: [i32; 12] = [0, 0, 1, 1, 2, 2, 3, 3, 4, 4, 4, 5];
+/// Rows sent by a clear that leaves the board empty, on top of the rest of the attack.
+const PERFECT_CLEAR_ATTACK: i32 = 10;
+
+/// Rows of garbage a clear sends to the opponent, following the guideline attack table.
+pub fn lines_sent(
+    spin: Spin,
+    cleared: i32,
+    is_back_to_back: bool,
+    combo: u32,
+    is_perfect_clear: bool,
+) -> i32 {
+    if cleared == 0 {
+        return 0;
+    }
+
+    let mut rows = match (spin, cleared) {
+        (Spin::None, 1) => 0,
+        (Spin::None, 2) => 1,
+        (Spin::None, 3) => 2,
+        (Spin::None, _) => 4,
+        (Spin::Mini, 1) => 0,
+        (Spin::Mini, _) => 1,
+        (Spin::Full, 1) => 2,
+        (Spin::Full, 2) => 4,
+        (Spin::Full, _) => 6,
+    };
+    if is_back_to_back {
+        rows += 1;
+    }
+    rows += COMBO_ATTACK[(combo as usize).min(COMBO_ATTACK.len() - 1)];
+    if is_perfect_clear {
+        rows += PERFECT_CLEAR_ATTACK;
+    }
+    rows
+}
@@ -0,0 +1,94 @@
+use crate::piece::Piece;
+
+pub const BOARD_WIDTH: i32 = 10;
+pub const BOARD_HEIGHT: i32 = 20;
+
+const WIDTH: usize = BOARD_WIDTH as usize;
+const HEIGHT: usize = BOARD_HEIGHT as usize;
+
+/// What fills a locked cell.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Block {
+    Piece(Piece),
+    /// A cell of a garbage row, which belongs to no piece.
+    Garbage,
+}
+
+/// Locked cells of the playing field, row by row from the top. Cells above the field
+/// (negative `y`) are never stored.
+#[derive(Clone)]
+pub struct Board {
+    rows: [[Option<Block>; WIDTH]; HEIGHT],
+}
+
+impl Default for Board {
+    fn default() -> Self {
+        Board {
+            rows: [[None; WIDTH]; HEIGHT],
+        }
+    }
+}
+
+impl Board {
+    pub fn get(&self, x: i32, y: i32) -> Option<Block> {
+        if x < 0 || y < 0 || x >= WIDTH as i32 || y >= HEIGHT as i32 {
+            return None;
+        }
+        self.rows[y as usize][x as usize]
+    }
+
+    pub fn set(&mut self, x: i32, y: i32, block: Block) {
+        if x >= 0 && y >= 0 && x < WIDTH as i32 && y < HEIGHT as i32 {
+            self.rows[y as usize][x as usize] = Some(block);
+        }
+    }
+
+    /// Removes every full row, shifts the rows above it down and returns the number of
+    /// removed rows.
+    pub fn clear_full_rows(&mut self) -> i32 {
+        let mut write = HEIGHT;
+        for read in (0..HEIGHT).rev() {
+            if self.rows[read].iter().all(|cell| cell.is_some()) {
+                continue;
+            }
+            write -= 1;
+            self.rows[write] = self.rows[read];
+        }
+        let cleared = write;
+        for row in self.rows.iter_mut().take(cleared) {
+            *row = [None; WIDTH];
+        }
+        cleared as i32
+    }
+
+    /// Shifts every row up by one and fills the bottom row with garbage except for the
+    /// `hole` column. Returns whether a locked cell was pushed off the top.
+    pub fn push_garbage(&mut self, hole: i32) -> bool {
+        let overflowed = self.rows[0].iter().any(|cell| cell.is_some());
+        self.rows.rotate_left(1);
+        for (x, cell) in self.rows[HEIGHT - 1].iter_mut().enumerate() {
+            *cell = (x as i32 != hole).then_some(Block::Garbage);
+        }
+        overflowed
+    }
+
+    /// How many rows still hold garbage.
+    pub fn garbage_rows(&self) -> i32 {
+        self.rows
+            .iter()
+            .filter(|row| row.contains(&Some(Block::Garbage)))
+            .count() as i32
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.cells().next().is_none()
+    }
+
+    pub fn cells(&self) -> impl Iterator<Item = (i32, i32, Block)> + '_ {
+        self.rows.iter().enumerate().flat_map(|(y, row)| {
+            row.iter()
+                .enumerate()
+                .filter_map(move |(x, cell)| cell.map(|block| (x as i32, y as i32, block)))
+        })
+    }
+}
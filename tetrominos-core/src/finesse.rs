@@ -0,0 +1,73 @@
+use std::collections::{HashSet, VecDeque};
+
+use crate::board::BOARD_WIDTH;
+use crate::piece::Piece;
+use crate::rotation::RotationSystem;
+
+/// Fewest presses that take `piece` from its spawn column to the placement it has at
+/// `angle` and `x`, counting a held side move that slides to the wall as one press.
+/// Placements covering the same cells count as the same, so that symmetric pieces are not
+/// asked to turn. Searched on an empty board, the way finesse is usually judged.
+pub fn minimal_inputs(
+    rotation: &dyn RotationSystem,
+    piece: Piece,
+    spawn_x: i32,
+    angle: u8,
+    x: i32,
+) -> u32 {
+    let target = footprint(rotation, piece, angle, x);
+    let fits = |angle: u8, x: i32| {
+        rotation
+            .get_tiles(&piece, angle, x, 0)
+            .iter()
+            .all(|tile| (0..BOARD_WIDTH).contains(&tile.0))
+    };
+
+    let mut visited = HashSet::from([(0, spawn_x)]);
+    let mut queue = VecDeque::from([(0u8, spawn_x, 0u32)]);
+    while let Some((angle, x, presses)) = queue.pop_front() {
+        if footprint(rotation, piece, angle, x) == target {
+            return presses;
+        }
+
+        let mut next = Vec::new();
+        for delta_x in [-1, 1] {
+            if fits(angle, x + delta_x) {
+                next.push((angle, x + delta_x));
+                let mut wall_x = x + delta_x;
+                while fits(angle, wall_x + delta_x) {
+                    wall_x += delta_x;
+                }
+                next.push((angle, wall_x));
+            }
+        }
+        for turns in 1..=3 {
+            let new_angle = (angle + turns) % 4;
+            if let Some((dx, _)) = rotation
+                .kicks(&piece, angle, new_angle)
+                .into_iter()
+                .find(|(dx, _)| fits(new_angle, x + dx))
+            {
+                next.push((new_angle, x + dx));
+            }
+        }
+
+        for state in next {
+            if visited.insert(state) {
+                queue.push_back((state.0, state.1, presses + 1));
+            }
+        }
+    }
+    0
+}
+
+/// Cells covered by the piece, shifted to the top row so that only columns and shape count.
+fn footprint(rotation: &dyn RotationSystem, piece: Piece, angle: u8, x: i32) -> Vec<(i32, i32)> {
+    let mut tiles = rotation.get_tiles(&piece, angle, x, 0);
+    let top = tiles.iter().map(|tile| tile.1).min().unwrap_or_default();
+    for tile in tiles.iter_mut() {
+        tile.1 -= top;
+    }
+    tiles.sort_unstable();
+    tiles
+}
@@ -0,0 +1,1467 @@
+use std::collections::VecDeque;
+use std::time::Duration;
+
+use derive_more::Constructor;
+
+use crate::attack;
+use crate::board::{Block, Board, BOARD_HEIGHT, BOARD_WIDTH};
+use crate::finesse;
+use crate::garbage::GarbageHoles;
+use crate::generator::PieceGenerator;
+use crate::gravity::{GravityTable, FRAMES_PER_SECOND, MAX_GRAVITY};
+use crate::mode::{Ending, Mode};
+use crate::piece::Piece;
+use crate::rotation::RotationSystem;
+use crate::statistics::Statistics;
+
+pub struct Rules {
+    pub mode: Mode,
+    pub gravity: GravityTable,
+    /// Lines to clear for each level, at least one.
+    pub lines_per_level: i32,
+    pub lock_delay: Duration,
+    pub max_lock_resets: u32,
+    /// Delayed auto shift, how long a side move has to be held before it starts repeating.
+    pub das: Duration,
+    /// Auto repeat rate, the time between repeated side moves. Zero slides to the wall at once.
+    pub arr: Duration,
+    /// How many times faster than gravity a held soft drop falls.
+    pub soft_drop_factor: f32,
+    /// Points for every row fallen during a soft drop.
+    pub soft_drop_points: i32,
+    /// Points for every row fallen in a hard drop.
+    pub hard_drop_points: i32,
+    /// Pause between a lock and the next spawn. DAS keeps charging during it.
+    pub entry_delay: Duration,
+    /// How many upcoming pieces are dealt ahead of time and can be previewed, at least one.
+    pub queue_length: usize,
+}
+
+impl Default for Rules {
+    fn default() -> Self {
+        Rules {
+            mode: Mode::Marathon,
+            gravity: GravityTable::default(),
+            lines_per_level: 10,
+            lock_delay: Duration::from_millis(500),
+            max_lock_resets: 15,
+            das: Duration::from_millis(167),
+            arr: Duration::from_millis(33),
+            soft_drop_factor: 20.,
+            soft_drop_points: 1,
+            hard_drop_points: 2,
+            entry_delay: Duration::ZERO,
+            queue_length: 5,
+        }
+    }
+}
+
+/// Buttons held down during a step. Rotations, hold and hard drop act once per press,
+/// moving and soft drop repeat while held.
+#[derive(Clone, Copy, Default, PartialEq, Eq, Debug)]
+pub struct Inputs {
+    pub left: bool,
+    pub right: bool,
+    pub soft_drop: bool,
+    pub hard_drop: bool,
+    pub rotate_cw: bool,
+    pub rotate_ccw: bool,
+    pub rotate_180: bool,
+    pub hold: bool,
+}
+
+impl Inputs {
+    fn pressed_since(&self, previous: &Inputs) -> Inputs {
+        Inputs {
+            left: self.left && !previous.left,
+            right: self.right && !previous.right,
+            soft_drop: self.soft_drop && !previous.soft_drop,
+            hard_drop: self.hard_drop && !previous.hard_drop,
+            rotate_cw: self.rotate_cw && !previous.rotate_cw,
+            rotate_ccw: self.rotate_ccw && !previous.rotate_ccw,
+            rotate_180: self.rotate_180 && !previous.rotate_180,
+            hold: self.hold && !previous.hold,
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum GameEvent {
+    /// The next queue was dealt a new piece.
+    NewPiece,
+    /// The active piece moved, rotated, spawned or locked.
+    NewPosition,
+    /// The hold slot changed.
+    Hold,
+    /// The active piece locked into the board.
+    Locked(Placement),
+    /// The locked piece was a T-spin clearing the given number of rows, possibly none.
+    TSpin(Spin, i32),
+    /// Full rows were removed after a lock, possibly none.
+    LinesCleared(i32),
+    /// A Tetris or a clearing T-spin followed another one, the count being how many in a
+    /// row did so.
+    BackToBack(u32),
+    /// Another piece in a row cleared rows, the count starting at 1 for the second one.
+    Combo(u32),
+    /// A clear left the board empty.
+    PerfectClear,
+    /// Garbage rows open at the `hole` column were pushed in from the bottom, lifting the
+    /// locked cells.
+    GarbageAdded {
+        rows: i32,
+        hole: i32,
+    },
+    /// A clear attacked the opponent with the given number of garbage rows, left over after
+    /// cancelling incoming garbage.
+    Attack(i32),
+    LevelUp(i32),
+    GameOver(Ending),
+}
+
+/// Where a piece locked, enough to lay it on a copy of the board.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Placement {
+    pub piece: Piece,
+    pub angle: u8,
+    pub x: i32,
+    pub y: i32,
+}
+
+/// How a T piece was spun into place, judged by the 3-corner rule when it locks.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Spin {
+    None,
+    Mini,
+    Full,
+}
+
+#[derive(Clone, Copy, Constructor, Debug)]
+pub struct GameState {
+    pub level: i32,
+    pub score: i32,
+    pub lines: i32,
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct PiecePosition {
+    pub piece: Piece,
+    pub angle: u8,
+    pub x: i32,
+    pub y: i32,
+    grounded_since: Option<Duration>,
+    lock_resets: u32,
+    /// Lowest row the piece has fallen to. Only falling past it gives back the lock delay
+    /// and the resets, so kicking the piece up and dropping it again does not.
+    lowest_y: i32,
+    /// Set when the last successful action was a rotation, cleared by any move.
+    last_rotation: Option<LastRotation>,
+    /// Presses of rotations and side moves spent on the piece, to judge its finesse.
+    presses: u32,
+}
+
+#[derive(Clone, Copy, Debug)]
+struct LastRotation {
+    turns: u8,
+    /// Index into the offsets returned by `RotationSystem::kicks`.
+    kick: usize,
+}
+
+impl PiecePosition {
+    /// Restarts the lock delay of a grounded piece after a move or rotation, as long as
+    /// the piece has resets left.
+    fn reset_lock_delay(&mut self, now: Duration, max_resets: u32) {
+        if self.grounded_since.is_some() && self.lock_resets < max_resets {
+            self.grounded_since = Some(now);
+            self.lock_resets += 1;
+        }
+    }
+}
+
+pub struct Game {
+    rules: Rules,
+    generator: Box<dyn PieceGenerator>,
+    rotation: Box<dyn RotationSystem>,
+    board: Board,
+    garbage_holes: GarbageHoles,
+    /// Batches of garbage rows received from an opponent and not yet pushed in, oldest
+    /// first.
+    incoming: VecDeque<i32>,
+    position: Option<PiecePosition>,
+    queue: VecDeque<Piece>,
+    hold: Option<Piece>,
+    is_hold_locked: bool,
+    state: GameState,
+    statistics: Statistics,
+    ending: Option<Ending>,
+    /// Consecutive pieces that cleared rows.
+    clearing_streak: u32,
+    /// Consecutive Tetrises and clearing T-spins, not broken by pieces clearing nothing.
+    difficult_streak: u32,
+    elapsed: Duration,
+    fall_progress: f32,
+    spawn_at: Option<Duration>,
+    /// When the last garbage row rose in modes where garbage rises on its own.
+    garbage_risen_at: Duration,
+    shift_direction: i32,
+    shift_charge: Duration,
+    last_inputs: Inputs,
+    events: Vec<GameEvent>,
+}
+
+impl Game {
+    pub fn new(
+        rules: Rules,
+        mut generator: Box<dyn PieceGenerator>,
+        rotation: Box<dyn RotationSystem>,
+        seed: u64,
+    ) -> Game {
+        assert!(
+            rules.lines_per_level > 0,
+            "Lines per level has to be at least one"
+        );
+        let queue = (0..rules.queue_length.max(1))
+            .map(|_| generator.next())
+            .collect();
+        let mut game = Game {
+            rules,
+            generator,
+            rotation,
+            board: Board::default(),
+            garbage_holes: GarbageHoles::new(seed),
+            incoming: VecDeque::new(),
+            position: None,
+            queue,
+            hold: None,
+            is_hold_locked: false,
+            state: GameState::new(1, 0, 0),
+            statistics: Statistics::default(),
+            ending: None,
+            clearing_streak: 0,
+            difficult_streak: 0,
+            elapsed: Duration::ZERO,
+            fall_progress: 0.,
+            spawn_at: None,
+            garbage_risen_at: Duration::ZERO,
+            shift_direction: 0,
+            shift_charge: Duration::ZERO,
+            last_inputs: Inputs::default(),
+            events: Vec::new(),
+        };
+        if let Mode::Cheese { rows } = game.rules.mode {
+            for _ in 0..rows {
+                let hole = game.garbage_holes.next_hole();
+                game.board.push_garbage(hole);
+            }
+        }
+        game.spawn_next();
+        game
+    }
+
+    /// Advances the game by `dt` with `inputs` held down.
+    pub fn step(&mut self, inputs: Inputs, dt: Duration) {
+        if self.is_over() {
+            return;
+        }
+        self.elapsed += dt;
+        if let Mode::Ultra { duration } = self.rules.mode {
+            if self.elapsed >= duration {
+                self.elapsed = duration;
+                self.end(Ending::TimeUp);
+                return;
+            }
+        }
+        if self.next_garbage().is_some_and(|wait| wait.is_zero()) {
+            self.garbage_risen_at = self.elapsed;
+            let hole = self.garbage_holes.next_hole();
+            self.rise_garbage(hole);
+            if self.is_over() {
+                return;
+            }
+        }
+        let pressed = inputs.pressed_since(&self.last_inputs);
+        self.last_inputs = inputs;
+
+        if self.spawn_at.is_some_and(|at| self.elapsed >= at) {
+            self.spawn_at = None;
+            self.spawn_next();
+        }
+
+        if pressed.hold {
+            self.hold_piece();
+        }
+        if let Some(position) = self.position.as_mut() {
+            position.presses += [pressed.rotate_cw, pressed.rotate_ccw, pressed.rotate_180]
+                .into_iter()
+                .filter(|pressed| *pressed)
+                .count() as u32;
+        }
+        if pressed.rotate_cw {
+            self.rotate(1);
+        }
+        if pressed.rotate_ccw {
+            self.rotate(3);
+        }
+        if pressed.rotate_180 {
+            self.rotate(2);
+        }
+        self.move_sideways(&inputs, dt);
+        self.descend(inputs.soft_drop, pressed.hard_drop, dt);
+    }
+
+    /// Takes the events emitted since the last call, oldest first.
+    pub fn drain_events(&mut self) -> Vec<GameEvent> {
+        std::mem::take(&mut self.events)
+    }
+
+    pub fn board(&self) -> &Board {
+        &self.board
+    }
+
+    pub fn rotation(&self) -> &dyn RotationSystem {
+        self.rotation.as_ref()
+    }
+
+    pub fn position(&self) -> Option<&PiecePosition> {
+        self.position.as_ref()
+    }
+
+    /// Upcoming pieces, the one to spawn next first.
+    pub fn queue(&self) -> &VecDeque<Piece> {
+        &self.queue
+    }
+
+    pub fn hold(&self) -> Option<Piece> {
+        self.hold
+    }
+
+    pub fn state(&self) -> &GameState {
+        &self.state
+    }
+
+    pub fn statistics(&self) -> &Statistics {
+        &self.statistics
+    }
+
+    /// Time played, which stops when the game is over.
+    pub fn elapsed(&self) -> Duration {
+        self.elapsed
+    }
+
+    pub fn is_over(&self) -> bool {
+        self.ending.is_some()
+    }
+
+    /// Why the game ended, if it did.
+    pub fn ending(&self) -> Option<Ending> {
+        self.ending
+    }
+
+    pub fn mode(&self) -> Mode {
+        self.rules.mode
+    }
+
+    /// Time left before a timed mode ends.
+    pub fn remaining(&self) -> Option<Duration> {
+        match self.rules.mode {
+            Mode::Ultra { duration } => Some(duration.saturating_sub(self.elapsed)),
+            _ => None,
+        }
+    }
+
+    /// Queues `rows` of garbage from an opponent. They are pushed in after the next piece
+    /// that locks without clearing, unless attacks cancel them first.
+    pub fn receive_garbage(&mut self, rows: i32) {
+        if rows > 0 && !self.is_over() {
+            self.incoming.push_back(rows);
+        }
+    }
+
+    /// Garbage rows received and not yet pushed in.
+    pub fn incoming_garbage(&self) -> i32 {
+        self.incoming.iter().sum()
+    }
+
+    /// Time left before the next garbage row rises, in modes where garbage rises on its own.
+    pub fn next_garbage(&self) -> Option<Duration> {
+        let interval = self
+            .rules
+            .mode
+            .garbage_interval(self.statistics.garbage_rows)?;
+        Some((self.garbage_risen_at + interval).saturating_sub(self.elapsed))
+    }
+
+    /// Tiles of the active piece, empty between a lock and the next spawn.
+    pub fn piece_tiles(&self) -> Vec<(i32, i32)> {
+        match &self.position {
+            Some(position) => self.tiles(position),
+            None => Vec::new(),
+        }
+    }
+
+    /// Tiles the active piece would cover after a hard drop.
+    pub fn ghost_tiles(&self) -> Vec<(i32, i32)> {
+        match &self.position {
+            Some(position) => self.rotation.get_tiles(
+                &position.piece,
+                position.angle,
+                position.x,
+                self.drop_y(position),
+            ),
+            None => Vec::new(),
+        }
+    }
+
+    fn tiles(&self, position: &PiecePosition) -> Vec<(i32, i32)> {
+        self.rotation
+            .get_tiles(&position.piece, position.angle, position.x, position.y)
+    }
+
+    fn fits(&self, piece: &Piece, angle: u8, x: i32, y: i32) -> bool {
+        self.rotation
+            .get_tiles(piece, angle, x, y)
+            .into_iter()
+            .all(|(x, y)| {
+                (0..BOARD_WIDTH).contains(&x) && y < BOARD_HEIGHT && self.board.get(x, y).is_none()
+            })
+    }
+
+    fn drop_y(&self, position: &PiecePosition) -> i32 {
+        let mut y = position.y;
+        while self.fits(&position.piece, position.angle, position.x, y + 1) {
+            y += 1;
+        }
+        y
+    }
+
+    fn next_from_queue(&mut self) -> Piece {
+        let piece = self.queue.pop_front().expect("The queue is never empty");
+        self.queue.push_back(self.generator.next());
+        self.events.push(GameEvent::NewPiece);
+        piece
+    }
+
+    fn spawn_next(&mut self) {
+        let piece = self.next_from_queue();
+        self.move_to_spawn(piece);
+    }
+
+    /// Places `piece` in its spawn orientation, centered and just above the visible rows.
+    fn move_to_spawn(&mut self, piece: Piece) {
+        let y = self.spawn_y(&piece);
+        self.position = Some(PiecePosition {
+            piece,
+            angle: 0,
+            x: spawn_x(&piece),
+            y,
+            grounded_since: None,
+            lock_resets: 0,
+            lowest_y: y,
+            last_rotation: None,
+            presses: 0,
+        });
+        self.events.push(GameEvent::NewPosition);
+    }
+
+    /// Row `piece` spawns at, its lowest tiles right above the visible rows.
+    fn spawn_y(&self, piece: &Piece) -> i32 {
+        let x = spawn_x(piece);
+        let mut y = -5;
+        while !self
+            .rotation
+            .get_tiles(piece, 0, x, y + 1)
+            .iter()
+            .any(|tile| tile.1 >= 0)
+        {
+            y += 1;
+        }
+        y
+    }
+
+    /// Pushes a garbage row open at `hole` in from the bottom. The active piece is lifted
+    /// with the stack when the row would otherwise overlap it. The game ends buried when
+    /// the rise pushes locked cells above the visible rows.
+    fn rise_garbage(&mut self, hole: i32) {
+        let is_buried = self.board.push_garbage(hole);
+        self.statistics.garbage_rows += 1;
+        self.events.push(GameEvent::GarbageAdded { rows: 1, hole });
+        if is_buried {
+            self.end(Ending::Buried);
+            return;
+        }
+
+        if let Some(mut position) = self.position {
+            if !self.fits(&position.piece, position.angle, position.x, position.y) {
+                position.y -= 1;
+                self.position = Some(position);
+                self.events.push(GameEvent::NewPosition);
+            }
+        }
+    }
+
+    /// Pushes in every batch of incoming garbage, each batch open at a single column.
+    fn take_incoming_garbage(&mut self) {
+        while let Some(rows) = self.incoming.pop_front() {
+            let hole = self.garbage_holes.next_hole();
+            for _ in 0..rows {
+                self.rise_garbage(hole);
+                if self.is_over() {
+                    return;
+                }
+            }
+        }
+    }
+
+    /// Cancels incoming garbage with an attack of `rows` and sends what is left over.
+    fn attack(&mut self, mut rows: i32) {
+        while rows > 0 {
+            let Some(batch) = self.incoming.front_mut() else {
+                break;
+            };
+            let cancelled = rows.min(*batch);
+            *batch -= cancelled;
+            rows -= cancelled;
+            if *batch == 0 {
+                self.incoming.pop_front();
+            }
+        }
+        if rows > 0 {
+            self.statistics.garbage_sent += rows as u32;
+            self.events.push(GameEvent::Attack(rows));
+        }
+    }
+
+    fn hold_piece(&mut self) {
+        let Some(position) = self.position else {
+            return;
+        };
+        if self.is_hold_locked {
+            return;
+        }
+
+        let next = match self.hold {
+            Some(piece) => piece,
+            None => self.next_from_queue(),
+        };
+        self.hold = Some(position.piece);
+        self.is_hold_locked = true;
+        self.move_to_spawn(next);
+        self.events.push(GameEvent::Hold);
+    }
+
+    fn rotate(&mut self, turns: u8) {
+        let Some(mut position) = self.position else {
+            return;
+        };
+        let new_angle = (position.angle + turns) % 4;
+
+        for (kick, (dx, dy)) in self
+            .rotation
+            .kicks(&position.piece, position.angle, new_angle)
+            .into_iter()
+            .enumerate()
+        {
+            let new_x = position.x + dx;
+            let new_y = position.y + dy;
+            if self.fits(&position.piece, new_angle, new_x, new_y) {
+                position.angle = new_angle;
+                position.x = new_x;
+                position.y = new_y;
+                position.last_rotation = Some(LastRotation { turns, kick });
+                position.reset_lock_delay(self.elapsed, self.rules.max_lock_resets);
+                self.position = Some(position);
+                self.events.push(GameEvent::NewPosition);
+                break;
+            }
+        }
+    }
+
+    /// Moves once when a direction is pressed, then again after every ARR once the direction
+    /// has been held for DAS. The charge builds up even while there is no piece to move.
+    fn move_sideways(&mut self, inputs: &Inputs, dt: Duration) {
+        let direction = match (inputs.left, inputs.right) {
+            (true, false) => -1,
+            (false, true) => 1,
+            _ => 0,
+        };
+
+        if direction != self.shift_direction {
+            self.shift_direction = direction;
+            self.shift_charge = Duration::ZERO;
+            if direction != 0 {
+                if let Some(position) = self.position.as_mut() {
+                    position.presses += 1;
+                }
+                self.shift(direction);
+            }
+            return;
+        }
+        if direction == 0 {
+            return;
+        }
+
+        let charge_before = self.shift_charge;
+        self.shift_charge += dt;
+        if self.shift_charge < self.rules.das {
+            return;
+        }
+
+        if self.rules.arr.is_zero() {
+            while self.shift(direction) {}
+            return;
+        }
+        let repeats = self.auto_repeats(self.shift_charge) - self.auto_repeats(charge_before);
+        for _ in 0..repeats {
+            if !self.shift(direction) {
+                break;
+            }
+        }
+    }
+
+    /// Number of repeated moves earned by holding a direction for `charge`.
+    fn auto_repeats(&self, charge: Duration) -> u128 {
+        if charge < self.rules.das {
+            return 0;
+        }
+        (charge - self.rules.das).as_nanos() / self.rules.arr.as_nanos() + 1
+    }
+
+    fn shift(&mut self, delta_x: i32) -> bool {
+        let Some(mut position) = self.position else {
+            return false;
+        };
+
+        let new_x = position.x + delta_x;
+        if !self.fits(&position.piece, position.angle, new_x, position.y) {
+            return false;
+        }
+        position.x = new_x;
+        position.last_rotation = None;
+        position.reset_lock_delay(self.elapsed, self.rules.max_lock_resets);
+        self.position = Some(position);
+        self.events.push(GameEvent::NewPosition);
+        true
+    }
+
+    fn descend(&mut self, soft_drop: bool, hard_drop: bool, dt: Duration) {
+        let Some(mut position) = self.position else {
+            return;
+        };
+
+        let mut gravity = self.rules.gravity.rows_per_frame(self.state.level);
+        if soft_drop {
+            gravity = (gravity * self.rules.soft_drop_factor).min(MAX_GRAVITY);
+        }
+        self.fall_progress += gravity * dt.as_secs_f32() * FRAMES_PER_SECOND;
+        let mut rows = self.fall_progress.floor() as i32;
+        self.fall_progress -= rows as f32;
+
+        let mut on_ground = false;
+        loop {
+            let new_y = position.y + 1;
+            if !self.fits(&position.piece, position.angle, position.x, new_y) {
+                on_ground = true;
+                break;
+            }
+            if !hard_drop && rows <= 0 {
+                break;
+            }
+
+            position.y = new_y;
+            if new_y > position.lowest_y {
+                position.lowest_y = new_y;
+                position.grounded_since = None;
+                position.lock_resets = 0;
+            }
+            position.last_rotation = None;
+            self.events.push(GameEvent::NewPosition);
+            rows -= 1;
+
+            if hard_drop {
+                self.state.score += self.rules.hard_drop_points;
+            } else if soft_drop {
+                self.state.score += self.rules.soft_drop_points;
+            }
+        }
+
+        if !on_ground {
+            self.position = Some(position);
+            return;
+        }
+
+        let grounded_since = *position.grounded_since.get_or_insert(self.elapsed);
+        self.position = Some(position);
+        if hard_drop || self.elapsed - grounded_since >= self.rules.lock_delay {
+            self.lock(position);
+        }
+    }
+
+    /// Applies the 3-corner rule to a T piece about to lock. Three of the four corners around
+    /// its center have to be blocked after a rotation. It is a full T-spin when both corners
+    /// the T points towards are among them or when the last of the five SRS kicks was needed,
+    /// otherwise a mini.
+    fn spin(&self, position: &PiecePosition) -> Spin {
+        let Some(rotation) = position.last_rotation else {
+            return Spin::None;
+        };
+        if position.piece != Piece::T {
+            return Spin::None;
+        }
+
+        let is_blocked = |(dx, dy): (i32, i32)| {
+            let (x, y) = (position.x + dx, position.y + dy);
+            !(0..BOARD_WIDTH).contains(&x) || y >= BOARD_HEIGHT || self.board.get(x, y).is_some()
+        };
+        let corners = [(0, 0), (2, 0), (2, 2), (0, 2)];
+        if corners
+            .into_iter()
+            .filter(|corner| is_blocked(*corner))
+            .count()
+            < 3
+        {
+            return Spin::None;
+        }
+
+        // Corners are listed clockwise from the top left, so the pair the T points to at
+        // angle `a` starts at index `a`.
+        let angle = position.angle as usize;
+        let front = [corners[angle], corners[(angle + 1) % 4]];
+        if front.into_iter().all(is_blocked) || (rotation.turns != 2 && rotation.kick == 4) {
+            Spin::Full
+        } else {
+            Spin::Mini
+        }
+    }
+
+    fn lock(&mut self, position: PiecePosition) {
+        let spin = self.spin(&position);
+        let mut out_of_bounds = false;
+        for (x, y) in self.tiles(&position) {
+            if y < 0 {
+                out_of_bounds = true;
+            }
+            self.board.set(x, y, Block::Piece(position.piece));
+        }
+
+        self.position = None;
+        self.is_hold_locked = false;
+        self.statistics.count_piece(position.piece);
+        let fewest_presses = finesse::minimal_inputs(
+            self.rotation.as_ref(),
+            position.piece,
+            spawn_x(&position.piece),
+            position.angle,
+            position.x,
+        );
+        self.statistics.finesse_faults += position.presses.saturating_sub(fewest_presses);
+        self.events.push(GameEvent::Locked(Placement {
+            piece: position.piece,
+            angle: position.angle,
+            x: position.x,
+            y: position.y,
+        }));
+        self.events.push(GameEvent::NewPosition);
+
+        self.clear_rows(spin);
+
+        if self.is_over() {
+            return;
+        }
+        if out_of_bounds {
+            self.end(Ending::ToppedOut);
+            return;
+        }
+
+        if self.rules.entry_delay.is_zero() {
+            self.spawn_next();
+        } else {
+            self.spawn_at = Some(self.elapsed + self.rules.entry_delay);
+        }
+    }
+
+    fn clear_rows(&mut self, spin: Spin) {
+        let cleared = self.board.clear_full_rows();
+
+        let mut points = match (spin, cleared) {
+            (Spin::None, 1) => 100,
+            (Spin::None, 2) => 300,
+            (Spin::None, 3) => 500,
+            (Spin::None, 4) => 800,
+            (Spin::None, _) => 0,
+            (Spin::Mini, 0) => 100,
+            (Spin::Mini, 1) => 200,
+            (Spin::Mini, _) => 400,
+            (Spin::Full, 0) => 400,
+            (Spin::Full, 1) => 800,
+            (Spin::Full, 2) => 1200,
+            (Spin::Full, _) => 1600,
+        };
+
+        self.state.lines += cleared;
+        self.statistics.count_clear(cleared);
+        if spin != Spin::None {
+            self.events.push(GameEvent::TSpin(spin, cleared));
+        }
+        self.events.push(GameEvent::LinesCleared(cleared));
+
+        if cleared == 0 {
+            self.clearing_streak = 0;
+            self.state.score += self.state.level * points;
+            self.take_incoming_garbage();
+        } else {
+            if cleared == 4 || spin != Spin::None {
+                self.difficult_streak += 1;
+                if self.difficult_streak > 1 {
+                    points = points * 3 / 2;
+                    self.events
+                        .push(GameEvent::BackToBack(self.difficult_streak - 1));
+                }
+            } else {
+                self.difficult_streak = 0;
+            }
+
+            self.clearing_streak += 1;
+            let combo = self.clearing_streak - 1;
+            if combo > 0 {
+                points += 50 * combo as i32;
+                self.events.push(GameEvent::Combo(combo));
+            }
+
+            let is_perfect_clear = self.board.is_empty();
+            if is_perfect_clear {
+                points += match cleared {
+                    1 => 800,
+                    2 => 1200,
+                    3 => 1800,
+                    _ if self.difficult_streak > 1 => 3200,
+                    _ => 2000,
+                };
+                self.events.push(GameEvent::PerfectClear);
+            }
+            self.state.score += self.state.level * points;
+            self.attack(attack::lines_sent(
+                spin,
+                cleared,
+                self.difficult_streak > 1,
+                combo,
+                is_perfect_clear,
+            ));
+        }
+
+        let level = 1 + self.state.lines / self.rules.lines_per_level;
+        if self.rules.mode.levels_up() && level > self.state.level {
+            self.state.level = level;
+            self.events.push(GameEvent::LevelUp(level));
+        }
+
+        let is_completed = match self.rules.mode {
+            Mode::Sprint { lines } => self.state.lines >= lines,
+            Mode::Cheese { .. } => cleared > 0 && self.board.garbage_rows() == 0,
+            _ => false,
+        };
+        if is_completed {
+            self.end(Ending::Completed);
+        }
+    }
+
+    fn end(&mut self, ending: Ending) {
+        self.ending = Some(ending);
+        self.events.push(GameEvent::GameOver(ending));
+    }
+}
+
+/// Column a piece spawns at, centered on the board.
+fn spawn_x(piece: &Piece) -> i32 {
+    (BOARD_WIDTH - piece.get_shape().max_size) / 2
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rotation::Srs;
+
+    const FRAME: Duration = Duration::from_millis(1);
+
+    /// Deals `pieces` over and over.
+    struct Sequence(Vec<Piece>, usize);
+
+    impl PieceGenerator for Sequence {
+        fn next(&mut self) -> Piece {
+            let piece = self.0[self.1 % self.0.len()];
+            self.1 += 1;
+            piece
+        }
+    }
+
+    fn new_game(pieces: &[Piece]) -> Game {
+        new_game_with(Rules::default(), pieces)
+    }
+
+    fn new_game_with(rules: Rules, pieces: &[Piece]) -> Game {
+        Game::new(
+            rules,
+            Box::new(Sequence(pieces.to_vec(), 0)),
+            Box::new(Srs),
+            0,
+        )
+    }
+
+    /// Presses and releases `inputs`, one frame each.
+    fn press(game: &mut Game, inputs: Inputs) {
+        game.step(inputs, FRAME);
+        game.step(Inputs::default(), FRAME);
+    }
+
+    fn position(game: &Game) -> PiecePosition {
+        *game.position().expect("A piece is in play")
+    }
+
+    #[test]
+    fn spawns_centered_right_above_the_board() {
+        let game = new_game(&[Piece::T]);
+        let position = position(&game);
+
+        assert_eq!(position.piece, Piece::T);
+        assert_eq!((position.angle, position.x, position.y), (0, 3, -2));
+        assert_eq!(game.piece_tiles().iter().map(|tile| tile.1).max(), Some(-1));
+    }
+
+    #[test]
+    #[should_panic(expected = "Lines per level")]
+    fn zero_lines_per_level_is_rejected() {
+        new_game_with(
+            Rules {
+                lines_per_level: 0,
+                ..Rules::default()
+            },
+            &[Piece::T],
+        );
+    }
+
+    #[test]
+    fn shifts_stop_at_the_wall() {
+        let mut game = new_game(&[Piece::I]);
+        let right = Inputs {
+            right: true,
+            ..Inputs::default()
+        };
+        for _ in 0..BOARD_WIDTH {
+            press(&mut game, right);
+        }
+
+        assert_eq!(position(&game).x, 6);
+        assert_eq!(game.piece_tiles().iter().map(|tile| tile.0).max(), Some(9));
+    }
+
+    #[test]
+    fn held_shifts_repeat_every_arr_once_das_is_charged() {
+        let mut game = new_game_with(
+            Rules {
+                das: Duration::from_millis(100),
+                arr: Duration::from_millis(20),
+                ..Rules::default()
+            },
+            &[Piece::T],
+        );
+        let right = Inputs {
+            right: true,
+            ..Inputs::default()
+        };
+
+        game.step(right, FRAME);
+        assert_eq!(position(&game).x, 4);
+        game.step(right, Duration::from_millis(99));
+        assert_eq!(position(&game).x, 4);
+        game.step(right, Duration::from_millis(1));
+        assert_eq!(position(&game).x, 5);
+        game.step(right, Duration::from_millis(19));
+        assert_eq!(position(&game).x, 5);
+        game.step(right, Duration::from_millis(1));
+        assert_eq!(position(&game).x, 6);
+        game.step(right, Duration::from_millis(100));
+        assert_eq!(position(&game).x, 7);
+
+        game.step(Inputs::default(), FRAME);
+        game.step(right, FRAME);
+        assert_eq!(position(&game).x, 7);
+    }
+
+    #[test]
+    fn zero_arr_slides_to_the_wall_once_das_is_charged() {
+        let mut game = new_game_with(
+            Rules {
+                das: Duration::from_millis(100),
+                arr: Duration::ZERO,
+                ..Rules::default()
+            },
+            &[Piece::T],
+        );
+        let left = Inputs {
+            left: true,
+            ..Inputs::default()
+        };
+
+        game.step(left, FRAME);
+        game.step(left, Duration::from_millis(99));
+        assert_eq!(position(&game).x, 2);
+        game.step(left, Duration::from_millis(1));
+        assert_eq!(position(&game).x, 0);
+    }
+
+    #[test]
+    fn soft_drop_falls_faster_by_its_factor() {
+        let rules = || Rules {
+            gravity: GravityTable::new(vec![0.05]),
+            soft_drop_factor: 20.,
+            ..Rules::default()
+        };
+        let soft_drop = Inputs {
+            soft_drop: true,
+            ..Inputs::default()
+        };
+
+        let mut game = new_game_with(rules(), &[Piece::T]);
+        let spawn_y = position(&game).y;
+        game.step(Inputs::default(), Duration::from_millis(350));
+        assert_eq!(position(&game).y, spawn_y + 1);
+
+        let mut game = new_game_with(rules(), &[Piece::T]);
+        game.step(soft_drop, Duration::from_millis(55));
+        assert_eq!(position(&game).y, spawn_y + 3);
+    }
+
+    #[test]
+    fn drops_score_points_per_row_fallen() {
+        let rules = || Rules {
+            gravity: GravityTable::new(vec![0.05]),
+            soft_drop_points: 3,
+            hard_drop_points: 5,
+            ..Rules::default()
+        };
+
+        let mut game = new_game_with(rules(), &[Piece::T]);
+        game.step(
+            Inputs {
+                soft_drop: true,
+                ..Inputs::default()
+            },
+            Duration::from_millis(55),
+        );
+        assert_eq!(game.state().score, 3 * 3);
+
+        let mut game = new_game_with(rules(), &[Piece::T]);
+        let fallen = game.drop_y(&position(&game)) - position(&game).y;
+        game.step(
+            Inputs {
+                hard_drop: true,
+                ..Inputs::default()
+            },
+            FRAME,
+        );
+        assert_eq!(game.state().score, 5 * fallen);
+        assert_eq!(fallen, 20);
+    }
+
+    #[test]
+    fn shifts_and_rotations_into_the_stack_fail() {
+        let mut game = new_game(&[Piece::T]);
+        game.position.as_mut().unwrap().y = 10;
+        let tiles = game.piece_tiles();
+        for x in 0..BOARD_WIDTH {
+            for y in 0..BOARD_HEIGHT {
+                if !tiles.contains(&(x, y)) {
+                    game.board.set(x, y, Block::Garbage);
+                }
+            }
+        }
+        let before = position(&game);
+
+        for inputs in [
+            Inputs {
+                left: true,
+                ..Inputs::default()
+            },
+            Inputs {
+                right: true,
+                ..Inputs::default()
+            },
+            Inputs {
+                rotate_cw: true,
+                ..Inputs::default()
+            },
+            Inputs {
+                rotate_ccw: true,
+                ..Inputs::default()
+            },
+            Inputs {
+                rotate_180: true,
+                ..Inputs::default()
+            },
+        ] {
+            press(&mut game, inputs);
+        }
+
+        let after = position(&game);
+        assert_eq!(
+            (after.angle, after.x, after.y),
+            (before.angle, before.x, before.y)
+        );
+    }
+
+    #[test]
+    fn locks_once_the_lock_delay_runs_out() {
+        let mut game = new_game(&[Piece::O]);
+        let floor = game.drop_y(&position(&game));
+        game.position.as_mut().unwrap().y = floor;
+
+        game.step(Inputs::default(), FRAME);
+        game.step(Inputs::default(), Duration::from_millis(400));
+        assert!(!game
+            .drain_events()
+            .iter()
+            .any(|event| matches!(event, GameEvent::Locked(_))));
+
+        game.step(Inputs::default(), Duration::from_millis(200));
+        assert!(game
+            .drain_events()
+            .iter()
+            .any(|event| matches!(event, GameEvent::Locked(_))));
+        assert!(game.board().get(4, BOARD_HEIGHT - 1).is_some());
+        assert_eq!(game.statistics().pieces, 1);
+    }
+
+    #[test]
+    fn clears_a_filled_row() {
+        let mut game = new_game(&[Piece::I]);
+        for x in (0..3).chain(7..BOARD_WIDTH) {
+            game.board.set(x, BOARD_HEIGHT - 1, Block::Garbage);
+        }
+
+        press(
+            &mut game,
+            Inputs {
+                hard_drop: true,
+                ..Inputs::default()
+            },
+        );
+
+        assert!(game.drain_events().contains(&GameEvent::LinesCleared(1)));
+        assert!(game.board().is_empty());
+        assert_eq!(game.state().lines, 1);
+    }
+
+    /// Fills rows `from_y` to the bottom except for the `open` cells.
+    fn fill_rows_except(game: &mut Game, from_y: i32, open: &[(i32, i32)]) {
+        for x in 0..BOARD_WIDTH {
+            for y in from_y..BOARD_HEIGHT {
+                if !open.contains(&(x, y)) {
+                    game.board.set(x, y, Block::Garbage);
+                }
+            }
+        }
+    }
+
+    fn place(game: &mut Game, angle: u8, x: i32, y: i32) {
+        let position = game.position.as_mut().unwrap();
+        position.angle = angle;
+        position.x = x;
+        position.y = y;
+    }
+
+    fn hard_drop(game: &mut Game) {
+        press(
+            game,
+            Inputs {
+                hard_drop: true,
+                ..Inputs::default()
+            },
+        );
+    }
+
+    const ROTATE_AND_DROP: Inputs = Inputs {
+        left: false,
+        right: false,
+        soft_drop: false,
+        hard_drop: true,
+        rotate_cw: true,
+        rotate_ccw: false,
+        rotate_180: false,
+        hold: false,
+    };
+
+    #[test]
+    fn t_spin_double_with_both_front_corners_blocked_is_full() {
+        let mut game = new_game(&[Piece::T]);
+        fill_rows_except(&mut game, 18, &[(3, 18), (4, 18), (5, 18), (4, 19)]);
+        game.board.set(3, 17, Block::Garbage);
+        place(&mut game, 1, 3, 17);
+
+        press(&mut game, ROTATE_AND_DROP);
+
+        let events = game.drain_events();
+        assert!(events.contains(&GameEvent::TSpin(Spin::Full, 2)));
+        assert!(events.contains(&GameEvent::LinesCleared(2)));
+        assert_eq!(game.state().score, 1200);
+    }
+
+    #[test]
+    fn t_spin_with_one_front_corner_blocked_is_mini() {
+        // Against the left wall, pointing right with only the lower front corner blocked.
+        let mut game = new_game(&[Piece::T]);
+        fill_rows_except(&mut game, 19, &[(0, 19)]);
+        place(&mut game, 0, 0, 17);
+
+        press(&mut game, ROTATE_AND_DROP);
+
+        let events = game.drain_events();
+        assert!(events.contains(&GameEvent::TSpin(Spin::Mini, 1)));
+        assert!(events.contains(&GameEvent::LinesCleared(1)));
+        assert_eq!(game.state().score, 200);
+    }
+
+    #[test]
+    fn last_kick_upgrades_a_mini_to_a_full_t_spin() {
+        // Only the last kick fits, and the lower front corner it ends next to is open.
+        let mut game = new_game(&[Piece::T]);
+        let open: Vec<(i32, i32)> = Srs
+            .get_tiles(&Piece::T, 0, 5, 15)
+            .into_iter()
+            .chain(Srs.get_tiles(&Piece::T, 1, 4, 17))
+            .chain([(6, 19)])
+            .collect();
+        fill_rows_except(&mut game, 15, &open);
+        game.board.set(5, 14, Block::Garbage);
+        place(&mut game, 0, 5, 15);
+
+        press(&mut game, ROTATE_AND_DROP);
+
+        let events = game.drain_events();
+        assert!(events.contains(&GameEvent::Locked(Placement {
+            piece: Piece::T,
+            angle: 1,
+            x: 4,
+            y: 17,
+        })));
+        assert!(events.contains(&GameEvent::TSpin(Spin::Full, 2)));
+        assert_eq!(game.state().score, 1200);
+    }
+
+    #[test]
+    fn rotating_without_three_corners_is_no_t_spin() {
+        let mut game = new_game(&[Piece::T]);
+        fill_rows_except(&mut game, 19, &[(3, 19), (4, 19), (5, 19)]);
+
+        press(&mut game, ROTATE_AND_DROP);
+
+        let events = game.drain_events();
+        assert!(!events
+            .iter()
+            .any(|event| matches!(event, GameEvent::TSpin(..))));
+    }
+
+    #[test]
+    fn clears_in_a_row_score_a_combo_and_an_empty_board_a_perfect_clear() {
+        let mut game = new_game_with(
+            Rules {
+                hard_drop_points: 0,
+                ..Rules::default()
+            },
+            &[Piece::I],
+        );
+        let gap: Vec<(i32, i32)> = (17..BOARD_HEIGHT)
+            .flat_map(|y| (0..4).map(move |x| (x, y)))
+            .collect();
+        fill_rows_except(&mut game, 17, &gap);
+
+        let mut scores = Vec::new();
+        for _ in 0..3 {
+            let y = position(&game).y;
+            place(&mut game, 0, 0, y);
+            hard_drop(&mut game);
+            scores.push(game.state().score);
+        }
+
+        let events = game.drain_events();
+        assert!(events.contains(&GameEvent::Combo(1)));
+        assert!(events.contains(&GameEvent::Combo(2)));
+        assert!(events.contains(&GameEvent::PerfectClear));
+        assert_eq!(scores, vec![100, 250, 1250]);
+    }
+
+    #[test]
+    fn back_to_back_tetris_scores_half_again() {
+        // A piece clearing nothing in between breaks the combo but not the chain.
+        let mut game = new_game_with(
+            Rules {
+                hard_drop_points: 0,
+                ..Rules::default()
+            },
+            &[Piece::I, Piece::O],
+        );
+        let well: Vec<(i32, i32)> = (0..BOARD_HEIGHT).map(|y| (0, y)).collect();
+        fill_rows_except(&mut game, 12, &well);
+        game.board.set(5, 11, Block::Garbage);
+
+        let y = position(&game).y;
+        place(&mut game, 1, -2, y);
+        hard_drop(&mut game);
+        assert_eq!(game.state().score, 800);
+        hard_drop(&mut game);
+        let y = position(&game).y;
+        place(&mut game, 1, -2, y);
+        hard_drop(&mut game);
+
+        let events = game.drain_events();
+        assert!(events.contains(&GameEvent::BackToBack(1)));
+        assert!(!events
+            .iter()
+            .any(|event| matches!(event, GameEvent::Combo(_))));
+        assert_eq!(game.state().score, 800 + 1200);
+    }
+
+    #[test]
+    fn garbage_pushing_the_stack_over_the_top_buries() {
+        let mut game = new_game_with(
+            Rules {
+                mode: Mode::Survival,
+                ..Rules::default()
+            },
+            &[Piece::O],
+        );
+        for y in 1..BOARD_HEIGHT {
+            game.board.set(0, y, Block::Garbage);
+        }
+        let first_row = game.next_garbage().unwrap();
+
+        game.step(Inputs::default(), FRAME);
+        game.step(Inputs::default(), first_row - FRAME);
+        assert!(!game.is_over());
+        assert_eq!(game.board.get(0, 0), Some(Block::Garbage));
+
+        let second_row = game.next_garbage().unwrap();
+        game.step(Inputs::default(), second_row);
+        assert_eq!(game.ending(), Some(Ending::Buried));
+    }
+
+    #[test]
+    fn garbage_reaching_the_spawn_column_keeps_the_game_going() {
+        let mut game = new_game_with(
+            Rules {
+                mode: Mode::Survival,
+                ..Rules::default()
+            },
+            &[Piece::O],
+        );
+        // The column under the next O reaches the top row once the garbage rises.
+        for y in 2..BOARD_HEIGHT {
+            game.board.set(4, y, Block::Garbage);
+        }
+        let first_row = game.next_garbage().unwrap();
+
+        game.step(Inputs::default(), FRAME);
+        game.step(Inputs::default(), first_row - FRAME);
+        assert_eq!(game.board.get(4, 1), Some(Block::Garbage));
+        assert_eq!(game.statistics().garbage_rows, 1);
+        assert!(!game.is_over());
+    }
+
+    /// A game whose next I piece is a Tetris down the left well, without a perfect clear.
+    fn tetris_ready() -> Game {
+        let mut game = new_game_with(
+            Rules {
+                mode: Mode::Versus,
+                ..Rules::default()
+            },
+            &[Piece::I],
+        );
+        let well: Vec<(i32, i32)> = (0..BOARD_HEIGHT).map(|y| (0, y)).collect();
+        fill_rows_except(&mut game, 16, &well);
+        game.board.set(5, 15, Block::Garbage);
+        let y = position(&game).y;
+        place(&mut game, 1, -2, y);
+        game
+    }
+
+    #[test]
+    fn attack_cancels_incoming_garbage_oldest_first() {
+        let mut game = tetris_ready();
+        game.receive_garbage(3);
+        game.receive_garbage(2);
+
+        hard_drop(&mut game);
+
+        assert_eq!(game.incoming_garbage(), 1);
+        let events = game.drain_events();
+        assert!(events.contains(&GameEvent::LinesCleared(4)));
+        assert!(!events
+            .iter()
+            .any(|event| matches!(event, GameEvent::Attack(_))));
+        assert_eq!(game.statistics().garbage_sent, 0);
+    }
+
+    #[test]
+    fn attack_left_over_after_cancelling_is_sent() {
+        let mut game = tetris_ready();
+        game.receive_garbage(1);
+
+        hard_drop(&mut game);
+
+        assert_eq!(game.incoming_garbage(), 0);
+        assert!(game.drain_events().contains(&GameEvent::Attack(3)));
+        assert_eq!(game.statistics().garbage_sent, 3);
+    }
+
+    #[test]
+    fn incoming_garbage_rises_after_a_piece_clearing_nothing() {
+        let mut game = new_game_with(
+            Rules {
+                mode: Mode::Versus,
+                ..Rules::default()
+            },
+            &[Piece::O],
+        );
+        game.receive_garbage(2);
+
+        hard_drop(&mut game);
+
+        assert_eq!(game.incoming_garbage(), 0);
+        assert_eq!(game.board().garbage_rows(), 2);
+        let hole = (0..BOARD_WIDTH)
+            .find(|x| game.board().get(*x, BOARD_HEIGHT - 1).is_none())
+            .unwrap();
+        assert!(game
+            .drain_events()
+            .contains(&GameEvent::GarbageAdded { rows: 1, hole }));
+        assert!(game.board().get(hole, BOARD_HEIGHT - 2).is_none());
+    }
+
+    #[test]
+    fn holds_once_per_piece() {
+        let mut game = new_game(&[Piece::T, Piece::I, Piece::O]);
+        let hold = Inputs {
+            hold: true,
+            ..Inputs::default()
+        };
+
+        press(&mut game, hold);
+        assert_eq!(game.hold(), Some(Piece::T));
+        assert_eq!(position(&game).piece, Piece::I);
+
+        press(&mut game, hold);
+        assert_eq!(game.hold(), Some(Piece::T));
+        assert_eq!(position(&game).piece, Piece::I);
+
+        press(
+            &mut game,
+            Inputs {
+                hard_drop: true,
+                ..Inputs::default()
+            },
+        );
+        assert_eq!(position(&game).piece, Piece::O);
+
+        press(&mut game, hold);
+        assert_eq!(game.hold(), Some(Piece::O));
+        assert_eq!(position(&game).piece, Piece::T);
+    }
+}
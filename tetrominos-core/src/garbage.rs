@@ -0,0 +1,30 @@
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+
+use crate::board::BOARD_WIDTH;
+
+/// Picks the open column of each garbage row, never the same column twice in a row so that
+/// every row has to be dug out on its own.
+pub struct GarbageHoles {
+    rng: ChaCha8Rng,
+    last: Option<i32>,
+}
+
+impl GarbageHoles {
+    /// Seeded apart from the piece generator, so that the same seed always lays the same
+    /// garbage without changing the pieces dealt.
+    pub fn new(seed: u64) -> GarbageHoles {
+        let mut rng = ChaCha8Rng::seed_from_u64(seed);
+        rng.set_stream(1);
+        GarbageHoles { rng, last: None }
+    }
+
+    pub fn next_hole(&mut self) -> i32 {
+        let hole = match self.last {
+            Some(last) => (last + self.rng.gen_range(1..BOARD_WIDTH)) % BOARD_WIDTH,
+            None => self.rng.gen_range(0..BOARD_WIDTH),
+        };
+        self.last = Some(hole);
+        hole
+    }
+}
@@ -0,0 +1,201 @@
+use std::collections::VecDeque;
+
+use rand::seq::SliceRandom;
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+
+use crate::piece::Piece;
+
+pub trait PieceGenerator: Send + Sync {
+    fn next(&mut self) -> Piece;
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum GeneratorKind {
+    SevenBag,
+    Random,
+    Nes,
+    Tgm,
+}
+
+impl GeneratorKind {
+    pub fn parse(name: &str) -> Option<GeneratorKind> {
+        match name {
+            "bag" => Some(GeneratorKind::SevenBag),
+            "random" => Some(GeneratorKind::Random),
+            "nes" => Some(GeneratorKind::Nes),
+            "tgm" => Some(GeneratorKind::Tgm),
+            _ => None,
+        }
+    }
+
+    /// The name `parse` takes.
+    pub fn name(&self) -> &'static str {
+        match self {
+            GeneratorKind::SevenBag => "bag",
+            GeneratorKind::Random => "random",
+            GeneratorKind::Nes => "nes",
+            GeneratorKind::Tgm => "tgm",
+        }
+    }
+
+    /// Creates the generator with every roll seeded from `seed`, so that the same seed
+    /// always deals the same pieces.
+    pub fn create(&self, seed: u64) -> Box<dyn PieceGenerator> {
+        let rng = ChaCha8Rng::seed_from_u64(seed);
+        match self {
+            GeneratorKind::SevenBag => Box::new(SevenBag::new(rng)),
+            GeneratorKind::Random => Box::new(PureRandom::new(rng)),
+            GeneratorKind::Nes => Box::new(NesReroll::new(rng)),
+            GeneratorKind::Tgm => Box::new(TgmHistory::new(rng)),
+        }
+    }
+}
+
+fn random_piece(rng: &mut ChaCha8Rng) -> Piece {
+    Piece::ALL[rng.gen_range(0..Piece::ALL.len())]
+}
+
+/// Every piece is equally likely on every draw, regardless of history.
+pub struct PureRandom {
+    rng: ChaCha8Rng,
+}
+
+impl PureRandom {
+    pub fn new(rng: ChaCha8Rng) -> PureRandom {
+        PureRandom { rng }
+    }
+}
+
+impl PieceGenerator for PureRandom {
+    fn next(&mut self) -> Piece {
+        random_piece(&mut self.rng)
+    }
+}
+
+/// Guideline randomizer: deals all seven pieces in a shuffled order before refilling.
+pub struct SevenBag {
+    rng: ChaCha8Rng,
+    bag: Vec<Piece>,
+}
+
+impl SevenBag {
+    pub fn new(rng: ChaCha8Rng) -> SevenBag {
+        SevenBag {
+            rng,
+            bag: Vec::with_capacity(Piece::ALL.len()),
+        }
+    }
+}
+
+impl PieceGenerator for SevenBag {
+    fn next(&mut self) -> Piece {
+        if self.bag.is_empty() {
+            self.bag.extend_from_slice(&Piece::ALL);
+            self.bag.shuffle(&mut self.rng);
+        }
+        self.bag.pop().unwrap()
+    }
+}
+
+/// NES randomizer: rolls an eighth "reroll" outcome alongside the seven pieces and rolls
+/// once more when it comes up or when the previous piece repeats.
+pub struct NesReroll {
+    rng: ChaCha8Rng,
+    last: Option<Piece>,
+}
+
+impl NesReroll {
+    pub fn new(rng: ChaCha8Rng) -> NesReroll {
+        NesReroll { rng, last: None }
+    }
+}
+
+impl PieceGenerator for NesReroll {
+    fn next(&mut self) -> Piece {
+        let roll = self.rng.gen_range(0..=Piece::ALL.len());
+        let piece = match Piece::ALL.get(roll) {
+            Some(piece) if Some(*piece) != self.last => *piece,
+            _ => random_piece(&mut self.rng),
+        };
+        self.last = Some(piece);
+        piece
+    }
+}
+
+const TGM_HISTORY: [Piece; 4] = [Piece::Z, Piece::S, Piece::S, Piece::Z];
+const TGM_ROLLS: u32 = 6;
+
+/// TGM randomizer: rerolls up to a fixed number of times while the piece is one of the
+/// last four dealt. The first piece is never S, Z or O.
+pub struct TgmHistory {
+    rng: ChaCha8Rng,
+    history: VecDeque<Piece>,
+    first: bool,
+}
+
+impl TgmHistory {
+    pub fn new(rng: ChaCha8Rng) -> TgmHistory {
+        TgmHistory {
+            rng,
+            history: VecDeque::from(TGM_HISTORY),
+            first: true,
+        }
+    }
+}
+
+impl PieceGenerator for TgmHistory {
+    fn next(&mut self) -> Piece {
+        let piece = if self.first {
+            self.first = false;
+            *[Piece::I, Piece::J, Piece::L, Piece::T]
+                .choose(&mut self.rng)
+                .unwrap()
+        } else {
+            let mut piece = random_piece(&mut self.rng);
+            for _ in 1..TGM_ROLLS {
+                if !self.history.contains(&piece) {
+                    break;
+                }
+                piece = random_piece(&mut self.rng);
+            }
+            piece
+        };
+        self.history.pop_front();
+        self.history.push_back(piece);
+        piece
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const KINDS: [GeneratorKind; 4] = [
+        GeneratorKind::SevenBag,
+        GeneratorKind::Random,
+        GeneratorKind::Nes,
+        GeneratorKind::Tgm,
+    ];
+
+    fn deal(kind: GeneratorKind, seed: u64, count: usize) -> Vec<Piece> {
+        let mut generator = kind.create(seed);
+        (0..count).map(|_| generator.next()).collect()
+    }
+
+    #[test]
+    fn same_seed_replays_the_same_pieces() {
+        for kind in KINDS {
+            assert_eq!(deal(kind, 42, 140), deal(kind, 42, 140), "{:?}", kind);
+        }
+    }
+
+    #[test]
+    fn seven_bag_deals_every_piece_once_per_bag() {
+        for bag in deal(GeneratorKind::SevenBag, 7, 700).chunks(Piece::ALL.len()) {
+            for piece in Piece::ALL {
+                assert_eq!(bag.iter().filter(|dealt| **dealt == piece).count(), 1);
+            }
+        }
+    }
+}
@@ -0,0 +1,42 @@
+use crate::board::BOARD_HEIGHT;
+
+pub const FRAMES_PER_SECOND: f32 = 60.0;
+pub const MAX_GRAVITY: f32 = BOARD_HEIGHT as f32;
+
+/// Fall speed per level, in rows per frame ("G"). Levels past the end of the
+/// table keep the speed of the last entry.
+#[derive(Clone)]
+pub struct GravityTable {
+    rows_per_frame: Vec<f32>,
+}
+
+impl GravityTable {
+    pub fn new(rows_per_frame: Vec<f32>) -> GravityTable {
+        assert!(!rows_per_frame.is_empty(), "Gravity table can not be empty");
+        GravityTable { rows_per_frame }
+    }
+
+    /// Guideline curve: a row takes `(0.8 - (level - 1) * 0.007) ^ (level - 1)`
+    /// seconds, capped at instant (20G) gravity.
+    pub fn guideline(levels: i32) -> GravityTable {
+        GravityTable::new(
+            (1..=levels)
+                .map(|level| {
+                    let row_seconds = (0.8 - (level - 1) as f32 * 0.007).powi(level - 1);
+                    (1. / (row_seconds * FRAMES_PER_SECOND)).min(MAX_GRAVITY)
+                })
+                .collect(),
+        )
+    }
+
+    pub fn rows_per_frame(&self, level: i32) -> f32 {
+        let index = (level.max(1) as usize - 1).min(self.rows_per_frame.len() - 1);
+        self.rows_per_frame[index]
+    }
+}
+
+impl Default for GravityTable {
+    fn default() -> Self {
+        GravityTable::guideline(20)
+    }
+}
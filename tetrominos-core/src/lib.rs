@@ -0,0 +1,16 @@
+//! Rules of the game without any rendering or input handling, so that a game can be
+//! stepped, inspected and replayed without opening a window.
+
+pub mod attack;
+pub mod board;
+pub mod finesse;
+pub mod game;
+pub mod garbage;
+pub mod generator;
+pub mod gravity;
+pub mod mode;
+pub mod net;
+pub mod piece;
+pub mod rotation;
+pub mod statistics;
+pub mod versus;
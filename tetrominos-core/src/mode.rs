@@ -0,0 +1,72 @@
+use std::time::Duration;
+
+/// Time between the first garbage rows of survival.
+const SURVIVAL_START_INTERVAL: Duration = Duration::from_secs(4);
+/// Shortest time between garbage rows of survival.
+const SURVIVAL_MIN_INTERVAL: Duration = Duration::from_millis(500);
+/// How much shorter the wait for each garbage row of survival is than the one before.
+const SURVIVAL_SPEEDUP: f32 = 0.95;
+
+/// What a game is played for, deciding when it ends besides topping out.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Mode {
+    /// Endless, with gravity speeding up every level.
+    Marathon,
+    /// Race to clear `lines` rows under constant gravity.
+    Sprint { lines: i32 },
+    /// Score as much as possible before `duration` runs out.
+    Ultra { duration: Duration },
+    /// Dig out `rows` rows of garbage, each with a single hole.
+    Cheese { rows: i32 },
+    /// Last as long as possible while garbage rows rise ever faster from the bottom.
+    Survival,
+    /// Outlast an opponent, clears sending garbage to each other.
+    Versus,
+}
+
+impl Mode {
+    /// Reads a mode from its command line name, with its default goal.
+    pub fn parse(name: &str) -> Option<Mode> {
+        match name {
+            "marathon" => Some(Mode::Marathon),
+            "sprint" => Some(Mode::Sprint { lines: 40 }),
+            "ultra" => Some(Mode::Ultra {
+                duration: Duration::from_secs(120),
+            }),
+            "cheese" => Some(Mode::Cheese { rows: 10 }),
+            "survival" => Some(Mode::Survival),
+            "versus" => Some(Mode::Versus),
+            _ => None,
+        }
+    }
+
+    pub fn levels_up(&self) -> bool {
+        matches!(self, Mode::Marathon | Mode::Ultra { .. })
+    }
+
+    /// Wait before the next garbage row rises once `risen` rows have, in modes where
+    /// garbage rises on its own.
+    pub fn garbage_interval(&self, risen: u32) -> Option<Duration> {
+        match self {
+            Mode::Survival => Some(
+                SURVIVAL_START_INTERVAL
+                    .mul_f32(SURVIVAL_SPEEDUP.powi(risen as i32))
+                    .max(SURVIVAL_MIN_INTERVAL),
+            ),
+            _ => None,
+        }
+    }
+}
+
+/// Why a game ended.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Ending {
+    /// A piece locked above the visible rows.
+    ToppedOut,
+    /// The goal of the mode was reached.
+    Completed,
+    /// The time of the mode ran out.
+    TimeUp,
+    /// Rising garbage pushed locked cells above the visible rows.
+    Buried,
+}
@@ -0,0 +1,701 @@
+//! Versus over TCP. Messages are lines of text, each a keyword followed by its numbers.
+//! The joining side says `HELLO` with its protocol version, the host answers with its own
+//! and then `START` with the seed and the piece generator of the match. From there on
+//! each side tells the other where its pieces locked, which garbage it took and which
+//! garbage it sends.
+
+use std::collections::VecDeque;
+use std::fmt;
+use std::io::{self, Read, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream, ToSocketAddrs};
+use std::ops::RangeBounds;
+use std::str::FromStr;
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+use crate::board::{Block, Board, BOARD_HEIGHT, BOARD_WIDTH};
+use crate::game::{Game, GameEvent, Inputs, Placement};
+use crate::generator::GeneratorKind;
+use crate::piece::Piece;
+use crate::rotation::RotationSystem;
+
+/// Bumped whenever a message changes, so that mismatched builds refuse to play.
+pub const PROTOCOL_VERSION: u32 = 1;
+
+/// Most garbage rows one message may carry, more than any single clear sends.
+const MAX_ROWS: i32 = 2 * BOARD_HEIGHT;
+/// Farthest the corner of a placed piece's bounding box may lie outside the board, as
+/// with an upright I piece against the left wall.
+const PIECE_REACH: i32 = 4;
+/// Longest line a message may take, well past the longest message, so that a peer that
+/// never ends its line can not fill up memory.
+const MAX_LINE_LENGTH: usize = 64;
+/// How long a joiner may take to say hello before the host turns to the next one.
+const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(5);
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Message {
+    Hello {
+        version: u32,
+    },
+    /// Both sides play with the host's generator, so that the same seed deals the same
+    /// pieces.
+    Start {
+        seed: u64,
+        generator: GeneratorKind,
+    },
+    /// A piece of the sender locked.
+    Placement(Placement),
+    /// The sender pushed garbage rows open at `hole` into its own board.
+    Garbage {
+        rows: i32,
+        hole: i32,
+    },
+    /// The sender attacks with rows of garbage.
+    Attack {
+        rows: i32,
+    },
+    /// The sender topped out.
+    Over,
+}
+
+impl Message {
+    fn encode(&self) -> String {
+        match self {
+            Message::Hello { version } => format!("HELLO {}", version),
+            Message::Start { seed, generator } => {
+                format!("START {} {}", seed, generator.name())
+            }
+            Message::Placement(placement) => format!(
+                "PLACE {:?} {} {} {}",
+                placement.piece, placement.angle, placement.x, placement.y
+            ),
+            Message::Garbage { rows, hole } => format!("GARBAGE {} {}", rows, hole),
+            Message::Attack { rows } => format!("ATTACK {}", rows),
+            Message::Over => String::from("OVER"),
+        }
+    }
+
+    fn decode(line: &str) -> Result<Message, NetError> {
+        let malformed = || NetError::Protocol(line.to_string());
+        let words: Vec<&str> = line.split_whitespace().collect();
+        Ok(match words.as_slice() {
+            ["HELLO", version] => Message::Hello {
+                version: parse(version, line)?,
+            },
+            ["START", seed, generator] => Message::Start {
+                seed: parse(seed, line)?,
+                generator: GeneratorKind::parse(generator).ok_or_else(malformed)?,
+            },
+            ["PLACE", piece, angle, x, y] => Message::Placement(Placement {
+                piece: Piece::ALL
+                    .into_iter()
+                    .find(|known| format!("{:?}", known) == *piece)
+                    .ok_or_else(malformed)?,
+                angle: parse::<u8>(angle, line)? % 4,
+                x: parse_in(x, -PIECE_REACH..BOARD_WIDTH, line)?,
+                y: parse_in(y, -PIECE_REACH..BOARD_HEIGHT, line)?,
+            }),
+            ["GARBAGE", rows, hole] => Message::Garbage {
+                rows: parse_in(rows, 1..=MAX_ROWS, line)?,
+                hole: parse_in(hole, 0..BOARD_WIDTH, line)?,
+            },
+            ["ATTACK", rows] => Message::Attack {
+                rows: parse_in(rows, 1..=MAX_ROWS, line)?,
+            },
+            ["OVER"] => Message::Over,
+            _ => return Err(malformed()),
+        })
+    }
+}
+
+fn parse<T: FromStr>(word: &str, line: &str) -> Result<T, NetError> {
+    word.parse()
+        .map_err(|_| NetError::Protocol(line.to_string()))
+}
+
+/// Parses a number the other side sent and checks that it is in `range`, so that a broken
+/// or hostile peer can not push the game past the board.
+fn parse_in<T: FromStr + PartialOrd>(
+    word: &str,
+    range: impl RangeBounds<T>,
+    line: &str,
+) -> Result<T, NetError> {
+    let value = parse(word, line)?;
+    if !range.contains(&value) {
+        return Err(NetError::Protocol(line.to_string()));
+    }
+    Ok(value)
+}
+
+#[derive(Debug)]
+pub enum NetError {
+    Io(io::Error),
+    /// The other side closed the connection.
+    Disconnected,
+    /// The other side speaks another version of the protocol.
+    Version {
+        ours: u32,
+        theirs: u32,
+    },
+    /// The other side sent a line that is not a message, or a message out of turn.
+    Protocol(String),
+}
+
+impl fmt::Display for NetError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            NetError::Io(error) => write!(f, "{}", error),
+            NetError::Disconnected => write!(f, "Disconnected"),
+            NetError::Version { ours, theirs } => {
+                write!(f, "Protocol version {} against {}", ours, theirs)
+            }
+            NetError::Protocol(line) => write!(f, "Unexpected message: {}", line),
+        }
+    }
+}
+
+impl From<io::Error> for NetError {
+    fn from(error: io::Error) -> Self {
+        match error.kind() {
+            io::ErrorKind::ConnectionReset
+            | io::ErrorKind::ConnectionAborted
+            | io::ErrorKind::BrokenPipe
+            | io::ErrorKind::UnexpectedEof => NetError::Disconnected,
+            _ => NetError::Io(error),
+        }
+    }
+}
+
+/// A connection to the other side that never blocks, so that it can be polled every frame.
+pub struct Connection {
+    stream: TcpStream,
+    /// Bytes of a line not received in full yet.
+    partial: Vec<u8>,
+    /// Bytes sent but not taken by the socket yet, written out as its buffer frees up.
+    outbox: VecDeque<u8>,
+    inbox: VecDeque<Message>,
+    is_closed: bool,
+}
+
+impl Connection {
+    fn new(stream: TcpStream) -> Result<Connection, NetError> {
+        stream.set_nonblocking(true)?;
+        stream.set_nodelay(true)?;
+        Ok(Connection {
+            stream,
+            partial: Vec::new(),
+            outbox: VecDeque::new(),
+            inbox: VecDeque::new(),
+            is_closed: false,
+        })
+    }
+
+    /// Queues `message` and writes out as much of the queue as the socket takes without
+    /// waiting. The rest goes out on later sends and receives.
+    pub fn send(&mut self, message: &Message) -> Result<(), NetError> {
+        self.outbox
+            .extend(format!("{}\n", message.encode()).into_bytes());
+        self.flush()
+    }
+
+    fn flush(&mut self) -> Result<(), NetError> {
+        while !self.outbox.is_empty() {
+            let (pending, _) = self.outbox.as_slices();
+            match self.stream.write(pending) {
+                Ok(0) => return Err(NetError::Disconnected),
+                Ok(length) => {
+                    self.outbox.drain(..length);
+                }
+                Err(error) if error.kind() == io::ErrorKind::WouldBlock => break,
+                Err(error) if error.kind() == io::ErrorKind::Interrupted => {}
+                Err(error) => return Err(error.into()),
+            }
+        }
+        Ok(())
+    }
+
+    /// The oldest message not taken yet, none while nothing new arrived. Messages sent
+    /// before the other side closed the connection are still handed out before the
+    /// disconnect is. Once everything received is taken, the queue of sent messages is
+    /// written out further.
+    pub fn receive(&mut self) -> Result<Option<Message>, NetError> {
+        if self.inbox.is_empty() {
+            self.read_available()?;
+        }
+        if let Some(message) = self.inbox.pop_front() {
+            return Ok(Some(message));
+        }
+        if self.is_closed {
+            return Err(NetError::Disconnected);
+        }
+        self.flush()?;
+        Ok(None)
+    }
+
+    fn read_available(&mut self) -> Result<(), NetError> {
+        let mut buffer = [0; 1024];
+        while !self.is_closed {
+            match self.stream.read(&mut buffer) {
+                Ok(0) => self.is_closed = true,
+                Ok(length) => self.partial.extend_from_slice(&buffer[..length]),
+                Err(error) if error.kind() == io::ErrorKind::WouldBlock => break,
+                Err(error) if error.kind() == io::ErrorKind::Interrupted => {}
+                Err(error) => return Err(error.into()),
+            }
+        }
+
+        while let Some(end) = self.partial.iter().position(|byte| *byte == b'\n') {
+            if end > MAX_LINE_LENGTH {
+                return Err(self.overlong_line());
+            }
+            let line: Vec<u8> = self.partial.drain(..=end).collect();
+            let line = String::from_utf8_lossy(&line);
+            let line = line.trim();
+            if !line.is_empty() {
+                self.inbox.push_back(Message::decode(line)?);
+            }
+        }
+        if self.partial.len() > MAX_LINE_LENGTH {
+            return Err(self.overlong_line());
+        }
+        Ok(())
+    }
+
+    fn overlong_line(&self) -> NetError {
+        let start = String::from_utf8_lossy(&self.partial[..MAX_LINE_LENGTH]);
+        NetError::Protocol(format!("{}...", start))
+    }
+}
+
+/// Waits for opponents to join, one match at a time.
+pub struct Host {
+    listener: TcpListener,
+    /// Accepted connection that has not said hello yet, and when it was accepted.
+    joining: Option<(Connection, Instant)>,
+    handshake_timeout: Duration,
+}
+
+impl Host {
+    pub fn bind(address: impl ToSocketAddrs) -> Result<Host, NetError> {
+        let listener = TcpListener::bind(address)?;
+        listener.set_nonblocking(true)?;
+        Ok(Host {
+            listener,
+            joining: None,
+            handshake_timeout: HANDSHAKE_TIMEOUT,
+        })
+    }
+
+    pub fn local_addr(&self) -> Result<SocketAddr, NetError> {
+        Ok(self.listener.local_addr()?)
+    }
+
+    /// Starts a match with `seed` and `generator` against an opponent who joined and speaks
+    /// the same version of the protocol, none while nobody has. An opponent failing to do
+    /// so, or staying silent for too long, is turned away with an error and hosting goes on.
+    pub fn accept(
+        &mut self,
+        seed: u64,
+        generator: GeneratorKind,
+    ) -> Result<Option<Connection>, NetError> {
+        if self.joining.is_none() {
+            match self.listener.accept() {
+                Ok((stream, _)) => self.joining = Some((Connection::new(stream)?, Instant::now())),
+                Err(error) if error.kind() == io::ErrorKind::WouldBlock => return Ok(None),
+                Err(error) => return Err(error.into()),
+            }
+        }
+        let Some((connection, accepted_at)) = self.joining.as_mut() else {
+            return Ok(None);
+        };
+
+        let version = match connection.receive() {
+            Ok(None) if accepted_at.elapsed() > self.handshake_timeout => {
+                self.joining = None;
+                return Err(io::Error::new(io::ErrorKind::TimedOut, "No hello in time").into());
+            }
+            Ok(None) => return Ok(None),
+            Ok(Some(Message::Hello { version })) => version,
+            Ok(Some(message)) => {
+                self.joining = None;
+                return Err(NetError::Protocol(message.encode()));
+            }
+            Err(error) => {
+                self.joining = None;
+                return Err(error);
+            }
+        };
+
+        let (mut connection, _) = self.joining.take().expect("Checked above");
+        connection.send(&Message::Hello {
+            version: PROTOCOL_VERSION,
+        })?;
+        if version != PROTOCOL_VERSION {
+            return Err(NetError::Version {
+                ours: PROTOCOL_VERSION,
+                theirs: version,
+            });
+        }
+        connection.send(&Message::Start { seed, generator })?;
+        Ok(Some(connection))
+    }
+}
+
+/// A connection to a host, waiting for the match to start.
+pub struct Join {
+    /// Outcome of connecting, which runs on its own thread so that polling never waits.
+    connecting: Option<JoinHandle<io::Result<TcpStream>>>,
+    connection: Option<Connection>,
+    has_hello: bool,
+}
+
+impl Join {
+    /// Starts connecting to the host in the background, giving up on an unreachable host
+    /// after `timeout`. Says hello once connected.
+    pub fn connect(address: &str, timeout: Duration) -> Join {
+        let address = address.to_string();
+        Join {
+            connecting: Some(thread::spawn(move || connect_any(&address, timeout))),
+            connection: None,
+            has_hello: false,
+        }
+    }
+
+    /// The connection, the seed and the piece generator of the match once the host
+    /// started it.
+    pub fn poll(&mut self) -> Result<Option<(Connection, u64, GeneratorKind)>, NetError> {
+        if let Some(connecting) = self.connecting.take() {
+            if !connecting.is_finished() {
+                self.connecting = Some(connecting);
+                return Ok(None);
+            }
+            let stream = connecting.join().map_err(|_| NetError::Disconnected)??;
+            let mut connection = Connection::new(stream)?;
+            connection.send(&Message::Hello {
+                version: PROTOCOL_VERSION,
+            })?;
+            self.connection = Some(connection);
+        }
+
+        let Some(connection) = self.connection.as_mut() else {
+            return Ok(None);
+        };
+        while let Some(message) = connection.receive()? {
+            match message {
+                Message::Hello { version } if version == PROTOCOL_VERSION => self.has_hello = true,
+                Message::Hello { version } => {
+                    return Err(NetError::Version {
+                        ours: PROTOCOL_VERSION,
+                        theirs: version,
+                    })
+                }
+                Message::Start { seed, generator } if self.has_hello => {
+                    let connection = self.connection.take().expect("Checked above");
+                    return Ok(Some((connection, seed, generator)));
+                }
+                message => return Err(NetError::Protocol(message.encode())),
+            }
+        }
+        Ok(None)
+    }
+}
+
+/// Connects to the first address `address` resolves to that answers within `timeout`.
+fn connect_any(address: &str, timeout: Duration) -> io::Result<TcpStream> {
+    let mut last_error = None;
+    for address in address.to_socket_addrs()? {
+        match TcpStream::connect_timeout(&address, timeout) {
+            Ok(stream) => return Ok(stream),
+            Err(error) => last_error = Some(error),
+        }
+    }
+    Err(last_error.unwrap_or_else(|| io::Error::new(io::ErrorKind::NotFound, "No address")))
+}
+
+/// What is known of the opponent's game, rebuilt from the messages they send.
+#[derive(Default)]
+pub struct Opponent {
+    pub board: Board,
+    pub pieces: u32,
+    pub garbage_sent: u32,
+    pub is_over: bool,
+    /// Messages taken in so far, so that a drawing of the opponent can tell it is out of
+    /// date.
+    pub updates: u32,
+}
+
+impl Opponent {
+    fn place(&mut self, rotation: &dyn RotationSystem, placement: Placement) {
+        for (x, y) in
+            rotation.get_tiles(&placement.piece, placement.angle, placement.x, placement.y)
+        {
+            self.board.set(x, y, Block::Piece(placement.piece));
+        }
+        self.board.clear_full_rows();
+        self.pieces += 1;
+    }
+}
+
+/// A game played against an opponent on the other end of a connection.
+pub struct OnlineMatch {
+    game: Game,
+    connection: Connection,
+    opponent: Opponent,
+}
+
+impl OnlineMatch {
+    pub fn new(game: Game, connection: Connection) -> OnlineMatch {
+        OnlineMatch {
+            game,
+            connection,
+            opponent: Opponent::default(),
+        }
+    }
+
+    /// Advances the local game by `dt`, tells the opponent what happened on it and takes in
+    /// what the opponent sent. Fails once the opponent is gone or breaks the protocol.
+    pub fn step(&mut self, inputs: Inputs, dt: Duration) -> Result<Vec<GameEvent>, NetError> {
+        let mut events = Vec::new();
+        if !self.is_over() {
+            self.game.step(inputs, dt);
+            events = self.game.drain_events();
+        }
+
+        for event in &events {
+            let message = match *event {
+                GameEvent::Locked(placement) => Message::Placement(placement),
+                GameEvent::GarbageAdded { rows, hole } => Message::Garbage { rows, hole },
+                GameEvent::Attack(rows) => Message::Attack { rows },
+                GameEvent::GameOver(_) => Message::Over,
+                _ => continue,
+            };
+            self.connection.send(&message)?;
+        }
+
+        while let Some(message) = self.connection.receive()? {
+            self.opponent.updates += 1;
+            match message {
+                Message::Placement(placement) => {
+                    self.opponent.place(self.game.rotation(), placement)
+                }
+                Message::Garbage { rows, hole } => {
+                    for _ in 0..rows.min(BOARD_HEIGHT) {
+                        self.opponent.board.push_garbage(hole);
+                    }
+                }
+                Message::Attack { rows } => {
+                    self.opponent.garbage_sent =
+                        self.opponent.garbage_sent.saturating_add(rows as u32);
+                    self.game.receive_garbage(rows);
+                }
+                Message::Over => self.opponent.is_over = true,
+                message => return Err(NetError::Protocol(message.encode())),
+            }
+        }
+        Ok(events)
+    }
+
+    pub fn game(&self) -> &Game {
+        &self.game
+    }
+
+    pub fn opponent(&self) -> &Opponent {
+        &self.opponent
+    }
+
+    pub fn is_over(&self) -> bool {
+        self.game.is_over() || self.opponent.is_over
+    }
+
+    /// Whether the local player is the one left standing, once the match is over.
+    pub fn has_won(&self) -> Option<bool> {
+        self.is_over().then(|| !self.game.is_over())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::Rules;
+    use crate::mode::Mode;
+    use crate::rotation::Srs;
+    use std::time::Instant;
+
+    const SEED: u64 = 42;
+    const GENERATOR: GeneratorKind = GeneratorKind::Nes;
+    const FRAME: Duration = Duration::from_millis(1);
+
+    /// Polls `poll` until it has something, failing the test after a while.
+    fn wait_for<T>(mut poll: impl FnMut() -> Option<T>) -> T {
+        let deadline = Instant::now() + Duration::from_secs(2);
+        loop {
+            if let Some(value) = poll() {
+                return value;
+            }
+            assert!(Instant::now() < deadline, "Timed out");
+            thread::sleep(FRAME);
+        }
+    }
+
+    fn join(host: &mut Host) -> (Connection, Connection, u64, GeneratorKind) {
+        let address = host.local_addr().unwrap().to_string();
+        let mut join = Join::connect(&address, Duration::from_secs(1));
+        // The joiner only says hello while polled, so both sides take turns.
+        let mut hosted = None;
+        let (joined, seed, generator) = wait_for(|| {
+            if hosted.is_none() {
+                hosted = host.accept(SEED, GENERATOR).unwrap();
+            }
+            join.poll().unwrap()
+        });
+        let hosted = hosted.expect("Started before the host");
+        (hosted, joined, seed, generator)
+    }
+
+    fn new_match(connection: Connection, seed: u64, generator: GeneratorKind) -> OnlineMatch {
+        let rules = Rules {
+            mode: Mode::Versus,
+            ..Rules::default()
+        };
+        let game = Game::new(rules, generator.create(seed), Box::new(Srs), seed);
+        OnlineMatch::new(game, connection)
+    }
+
+    fn matches() -> (OnlineMatch, OnlineMatch) {
+        let mut host = Host::bind("127.0.0.1:0").unwrap();
+        let (hosted, joined, seed, generator) = join(&mut host);
+        (
+            new_match(hosted, SEED, GENERATOR),
+            new_match(joined, seed, generator),
+        )
+    }
+
+    /// Steps both sides until `done` holds for the second.
+    fn exchange(a: &mut OnlineMatch, b: &mut OnlineMatch, done: impl Fn(&OnlineMatch) -> bool) {
+        wait_for(|| {
+            a.step(Inputs::default(), FRAME).unwrap();
+            b.step(Inputs::default(), FRAME).unwrap();
+            done(b).then_some(())
+        });
+    }
+
+    #[test]
+    fn host_starts_every_joiner_with_its_seed_and_generator() {
+        let mut host = Host::bind("127.0.0.1:0").unwrap();
+        let (_, _, seed, generator) = join(&mut host);
+        assert_eq!((seed, generator), (SEED, GENERATOR));
+
+        // The host keeps listening for the next match.
+        let (_, _, seed, generator) = join(&mut host);
+        assert_eq!((seed, generator), (SEED, GENERATOR));
+    }
+
+    #[test]
+    fn host_turns_away_another_version() {
+        let mut host = Host::bind("127.0.0.1:0").unwrap();
+        let mut stream = TcpStream::connect(host.local_addr().unwrap()).unwrap();
+        stream.write_all(b"HELLO 999\n").unwrap();
+
+        let error = wait_for(|| match host.accept(SEED, GENERATOR) {
+            Ok(connection) => {
+                assert!(connection.is_none(), "Started a match");
+                None
+            }
+            Err(error) => Some(error),
+        });
+        assert!(matches!(
+            error,
+            NetError::Version {
+                ours: PROTOCOL_VERSION,
+                theirs: 999
+            }
+        ));
+
+        // The joiner still learns which version the host speaks.
+        let mut reply = String::new();
+        stream.read_to_string(&mut reply).unwrap();
+        assert_eq!(reply, format!("HELLO {}\n", PROTOCOL_VERSION));
+    }
+
+    #[test]
+    fn host_turns_away_a_silent_joiner() {
+        let mut host = Host::bind("127.0.0.1:0").unwrap();
+        host.handshake_timeout = Duration::from_millis(20);
+        let _silent = TcpStream::connect(host.local_addr().unwrap()).unwrap();
+
+        let error = wait_for(|| host.accept(SEED, GENERATOR).err());
+        assert!(matches!(error, NetError::Io(error) if error.kind() == io::ErrorKind::TimedOut));
+
+        // The next joiner gets in.
+        let (_, _, seed, _) = join(&mut host);
+        assert_eq!(seed, SEED);
+    }
+
+    #[test]
+    fn lines_past_the_length_limit_are_rejected() {
+        let mut host = Host::bind("127.0.0.1:0").unwrap();
+        let mut stream = TcpStream::connect(host.local_addr().unwrap()).unwrap();
+        stream.write_all(&[b'A'; 4 * MAX_LINE_LENGTH]).unwrap();
+
+        let error = wait_for(|| host.accept(SEED, GENERATOR).err());
+        assert!(matches!(error, NetError::Protocol(_)));
+    }
+
+    #[test]
+    fn placements_and_attacks_reach_the_opponent() {
+        let (mut a, mut b) = matches();
+        let drop = Inputs {
+            hard_drop: true,
+            ..Inputs::default()
+        };
+        a.step(drop, FRAME).unwrap();
+        exchange(&mut a, &mut b, |b| b.opponent().pieces == 1);
+        let cells = |board: &Board| board.cells().collect::<Vec<_>>();
+        assert_eq!(cells(&b.opponent().board), cells(a.game().board()));
+
+        a.connection.send(&Message::Attack { rows: 2 }).unwrap();
+        exchange(&mut a, &mut b, |b| b.opponent().garbage_sent == 2);
+        assert_eq!(b.game().incoming_garbage(), 2);
+    }
+
+    #[test]
+    fn over_ends_the_match() {
+        let (mut a, mut b) = matches();
+        a.connection.send(&Message::Over).unwrap();
+        exchange(&mut a, &mut b, OnlineMatch::is_over);
+        assert_eq!(b.has_won(), Some(true));
+    }
+
+    #[test]
+    fn dropped_connection_fails_the_match() {
+        let (a, mut b) = matches();
+        drop(a);
+        let error = wait_for(|| b.step(Inputs::default(), FRAME).err());
+        assert!(matches!(error, NetError::Disconnected));
+    }
+
+    #[test]
+    fn decode_rejects_numbers_off_the_board() {
+        for line in [
+            "PLACE T 0 2147483647 0",
+            "PLACE T 0 0 -100",
+            "GARBAGE 1 10",
+            "GARBAGE 100000 0",
+            "ATTACK 100000",
+            "ATTACK 0",
+            "START 1",
+            "START 1 shuffle",
+        ] {
+            assert!(
+                matches!(Message::decode(line), Err(NetError::Protocol(_))),
+                "{}",
+                line
+            );
+        }
+        assert_eq!(
+            Message::decode("ATTACK 4").unwrap(),
+            Message::Attack { rows: 4 }
+        );
+    }
+}
@@ -0,0 +1,74 @@
+use derive_more::Constructor;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Piece {
+    I,
+    L,
+    J,
+    O,
+    S,
+    Z,
+    T,
+}
+
+#[derive(Constructor)]
+pub struct Shape {
+    pub max_size: i32,
+    coords: Vec<(i32, i32)>,
+}
+
+impl Piece {
+    pub const ALL: [Piece; 7] = [
+        Piece::I,
+        Piece::L,
+        Piece::J,
+        Piece::O,
+        Piece::S,
+        Piece::Z,
+        Piece::T,
+    ];
+
+    pub fn get_tiles(&self, angle: u8, piece_x: i32, piece_y: i32) -> Vec<(i32, i32)> {
+        let original_shape = self.get_shape();
+        match angle % 4 {
+            0 => original_shape
+                .coords
+                .iter()
+                .map(|(x, y)| (*x + piece_x, *y + piece_y))
+                .collect(),
+            1 => original_shape
+                .coords
+                .iter()
+                .map(|(x, y)| (original_shape.max_size - 1 - y + piece_x, *x + piece_y))
+                .collect(),
+            2 => original_shape
+                .coords
+                .iter()
+                .map(|(x, y)| {
+                    (
+                        original_shape.max_size - 1 - x + piece_x,
+                        original_shape.max_size - 1 - y + piece_y,
+                    )
+                })
+                .collect(),
+            3 => original_shape
+                .coords
+                .iter()
+                .map(|(x, y)| (*y + piece_x, original_shape.max_size - 1 - x + piece_y))
+                .collect(),
+            u => panic!("Wrong angle: {}", u),
+        }
+    }
+
+    pub fn get_shape(&self) -> Shape {
+        match self {
+            Piece::I => Shape::new(4, vec![(0, 1), (1, 1), (2, 1), (3, 1)]),
+            Piece::L => Shape::new(3, vec![(2, 0), (0, 1), (1, 1), (2, 1)]),
+            Piece::J => Shape::new(3, vec![(0, 0), (0, 1), (1, 1), (2, 1)]),
+            Piece::O => Shape::new(2, vec![(0, 0), (1, 0), (0, 1), (1, 1)]),
+            Piece::S => Shape::new(3, vec![(1, 0), (2, 0), (0, 1), (1, 1)]),
+            Piece::Z => Shape::new(3, vec![(0, 0), (1, 0), (1, 1), (2, 1)]),
+            Piece::T => Shape::new(3, vec![(1, 0), (0, 1), (1, 1), (2, 1)]),
+        }
+    }
+}
@@ -0,0 +1,163 @@
+use crate::piece::Piece;
+
+pub trait RotationSystem: Send + Sync {
+    /// Tiles covered by `piece` in orientation `angle` with its bounding box at `x`, `y`.
+    fn get_tiles(&self, piece: &Piece, angle: u8, x: i32, y: i32) -> Vec<(i32, i32)> {
+        piece.get_tiles(angle, x, y)
+    }
+
+    /// Offsets tried in order when rotating `piece` from angle `from` to angle `to`.
+    /// The first offset that does not collide is taken, the rotation fails if none fits.
+    fn kicks(&self, piece: &Piece, from: u8, to: u8) -> Vec<(i32, i32)>;
+}
+
+/// Guideline Super Rotation System. Kick offsets are listed with y growing upwards as in
+/// the guideline tables and are flipped to the board's downward y when returned.
+pub struct Srs;
+
+const JLSTZ_KICKS: [[(i32, i32); 5]; 8] = [
+    [(0, 0), (-1, 0), (-1, 1), (0, -2), (-1, -2)], // 0 -> R
+    [(0, 0), (1, 0), (1, -1), (0, 2), (1, 2)],     // R -> 0
+    [(0, 0), (1, 0), (1, -1), (0, 2), (1, 2)],     // R -> 2
+    [(0, 0), (-1, 0), (-1, 1), (0, -2), (-1, -2)], // 2 -> R
+    [(0, 0), (1, 0), (1, 1), (0, -2), (1, -2)],    // 2 -> L
+    [(0, 0), (-1, 0), (-1, -1), (0, 2), (-1, 2)],  // L -> 2
+    [(0, 0), (-1, 0), (-1, -1), (0, 2), (-1, 2)],  // L -> 0
+    [(0, 0), (1, 0), (1, 1), (0, -2), (1, -2)],    // 0 -> L
+];
+
+const I_KICKS: [[(i32, i32); 5]; 8] = [
+    [(0, 0), (-2, 0), (1, 0), (-2, -1), (1, 2)], // 0 -> R
+    [(0, 0), (2, 0), (-1, 0), (2, 1), (-1, -2)], // R -> 0
+    [(0, 0), (-1, 0), (2, 0), (-1, 2), (2, -1)], // R -> 2
+    [(0, 0), (1, 0), (-2, 0), (1, -2), (-2, 1)], // 2 -> R
+    [(0, 0), (2, 0), (-1, 0), (2, 1), (-1, -2)], // 2 -> L
+    [(0, 0), (-2, 0), (1, 0), (-2, -1), (1, 2)], // L -> 2
+    [(0, 0), (1, 0), (-2, 0), (1, -2), (-2, 1)], // L -> 0
+    [(0, 0), (-1, 0), (2, 0), (-1, 2), (2, -1)], // 0 -> L
+];
+
+/// Half turns have no guideline table, these are the ones of TETR.IO's SRS+ shared by every
+/// piece, indexed by the starting angle.
+const HALF_TURN_KICKS: [[(i32, i32); 6]; 4] = [
+    [(0, 0), (0, 1), (1, 1), (-1, 1), (1, 0), (-1, 0)], // 0 -> 2
+    [(0, 0), (1, 0), (1, 2), (1, 1), (0, 2), (0, 1)],   // R -> L
+    [(0, 0), (0, -1), (-1, -1), (1, -1), (-1, 0), (1, 0)], // 2 -> 0
+    [(0, 0), (-1, 0), (-1, 2), (-1, 1), (0, 2), (0, 1)], // L -> R
+];
+
+impl RotationSystem for Srs {
+    fn kicks(&self, piece: &Piece, from: u8, to: u8) -> Vec<(i32, i32)> {
+        if *piece == Piece::O {
+            return vec![(0, 0)];
+        }
+
+        let from = from % 4;
+        let to = to % 4;
+        let offsets: &[(i32, i32)] = if to == (from + 1) % 4 {
+            self.quarter_turn_kicks(piece, 2 * from as usize)
+        } else if from == (to + 1) % 4 {
+            self.quarter_turn_kicks(piece, (2 * from as usize + 7) % 8)
+        } else if to == (from + 2) % 4 {
+            &HALF_TURN_KICKS[from as usize]
+        } else {
+            return vec![(0, 0)];
+        };
+        offsets.iter().map(|(x, y)| (*x, -*y)).collect()
+    }
+}
+
+impl Srs {
+    fn quarter_turn_kicks(&self, piece: &Piece, transition: usize) -> &'static [(i32, i32)] {
+        match piece {
+            Piece::I => &I_KICKS[transition],
+            _ => &JLSTZ_KICKS[transition],
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::board::{Block, Board, BOARD_HEIGHT, BOARD_WIDTH};
+
+    fn fits(board: &Board, piece: &Piece, angle: u8, x: i32, y: i32) -> bool {
+        Srs.get_tiles(piece, angle, x, y).into_iter().all(|(x, y)| {
+            (0..BOARD_WIDTH).contains(&x) && y < BOARD_HEIGHT && board.get(x, y).is_none()
+        })
+    }
+
+    /// Index and offset of the first kick that fits, as the game takes it.
+    fn first_kick(
+        board: &Board,
+        piece: Piece,
+        from: u8,
+        to: u8,
+        x: i32,
+        y: i32,
+    ) -> Option<(usize, (i32, i32))> {
+        Srs.kicks(&piece, from, to)
+            .into_iter()
+            .enumerate()
+            .find(|(_, (dx, dy))| fits(board, &piece, to, x + dx, y + dy))
+    }
+
+    #[test]
+    fn kicks_flip_the_guideline_y() {
+        assert_eq!(
+            Srs.kicks(&Piece::I, 0, 1),
+            vec![(0, 0), (-2, 0), (1, 0), (-2, 1), (1, -2)]
+        );
+        assert_eq!(
+            Srs.kicks(&Piece::T, 0, 1),
+            vec![(0, 0), (-1, 0), (-1, -1), (0, 2), (-1, 2)]
+        );
+        assert_eq!(Srs.kicks(&Piece::O, 0, 1), vec![(0, 0)]);
+    }
+
+    #[test]
+    fn i_piece_kicks_off_the_left_wall() {
+        // Upright against the left wall, lying down has to move two columns right.
+        let board = Board::default();
+        assert!(fits(&board, &Piece::I, 1, -2, 10));
+        assert!(!fits(&board, &Piece::I, 2, -2, 10));
+
+        assert_eq!(
+            first_kick(&board, Piece::I, 1, 2, -2, 10),
+            Some((2, (2, 0)))
+        );
+    }
+
+    #[test]
+    fn t_spin_triple_kick_drops_two_rows() {
+        // Only the cells of the T before and after the turn are open, so none but the last
+        // kick, one column left and two rows down, can take it.
+        let (x, y) = (4, 17);
+        let open: Vec<(i32, i32)> = Srs
+            .get_tiles(&Piece::T, 0, x + 1, y - 2)
+            .into_iter()
+            .chain(Srs.get_tiles(&Piece::T, 1, x, y))
+            .collect();
+        let mut board = Board::default();
+        for cell_x in 0..BOARD_WIDTH {
+            for cell_y in 0..BOARD_HEIGHT {
+                if !open.contains(&(cell_x, cell_y)) {
+                    board.set(cell_x, cell_y, Block::Garbage);
+                }
+            }
+        }
+
+        assert_eq!(
+            first_kick(&board, Piece::T, 0, 1, x + 1, y - 2),
+            Some((4, (-1, 2)))
+        );
+    }
+
+    #[test]
+    fn half_turns_use_the_srs_plus_table() {
+        assert_eq!(
+            Srs.kicks(&Piece::T, 0, 2),
+            vec![(0, 0), (0, -1), (1, -1), (-1, -1), (1, 0), (-1, 0)]
+        );
+    }
+}
@@ -0,0 +1,51 @@
+use std::time::Duration;
+
+use crate::piece::Piece;
+
+/// Tallies of a game beyond its score, for the stats panel and the game over summary.
+#[derive(Clone, Default, Debug)]
+pub struct Statistics {
+    pub pieces: u32,
+    piece_counts: [u32; Piece::ALL.len()],
+    /// Singles, doubles, triples and tetrises.
+    clears: [u32; 4],
+    /// Presses spent beyond the fewest needed to place each piece.
+    pub finesse_faults: u32,
+    /// Garbage rows pushed in from the bottom.
+    pub garbage_rows: u32,
+    /// Garbage rows attacked with, after cancelling incoming garbage.
+    pub garbage_sent: u32,
+}
+
+impl Statistics {
+    pub fn count_piece(&mut self, piece: Piece) {
+        self.pieces += 1;
+        self.piece_counts[piece as usize] += 1;
+    }
+
+    pub fn count_clear(&mut self, lines: i32) {
+        if (1..=4).contains(&lines) {
+            self.clears[lines as usize - 1] += 1;
+        }
+    }
+
+    /// How many pieces of a kind were placed.
+    pub fn pieces_of(&self, piece: Piece) -> u32 {
+        self.piece_counts[piece as usize]
+    }
+
+    /// How many clears removed exactly `lines` rows at once, from 1 to 4.
+    pub fn clears_of(&self, lines: i32) -> u32 {
+        match lines {
+            1..=4 => self.clears[lines as usize - 1],
+            _ => 0,
+        }
+    }
+
+    pub fn pieces_per_second(&self, elapsed: Duration) -> f32 {
+        if elapsed.is_zero() {
+            return 0.;
+        }
+        self.pieces as f32 / elapsed.as_secs_f32()
+    }
+}
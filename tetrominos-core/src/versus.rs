@@ -0,0 +1,54 @@
+use std::time::Duration;
+
+use crate::game::{Game, GameEvent, Inputs};
+
+/// Two games played against each other, the rows each one attacks with going to the other
+/// one as incoming garbage.
+pub struct Versus {
+    games: [Game; 2],
+}
+
+impl Versus {
+    pub fn new(games: [Game; 2]) -> Versus {
+        Versus { games }
+    }
+
+    /// Advances both games by `dt`, each with its own inputs, and returns the events of each
+    /// game. Nothing moves once either game is over.
+    pub fn step(&mut self, inputs: [Inputs; 2], dt: Duration) -> [Vec<GameEvent>; 2] {
+        if self.is_over() {
+            return Default::default();
+        }
+        for (game, inputs) in self.games.iter_mut().zip(inputs) {
+            game.step(inputs, dt);
+        }
+
+        let events = self.games.each_mut().map(|game| game.drain_events());
+        for (player, events) in events.iter().enumerate() {
+            for event in events {
+                if let GameEvent::Attack(rows) = event {
+                    self.games[1 - player].receive_garbage(*rows);
+                }
+            }
+        }
+        events
+    }
+
+    pub fn game(&self, player: usize) -> &Game {
+        &self.games[player]
+    }
+
+    pub fn is_over(&self) -> bool {
+        self.games.iter().any(Game::is_over)
+    }
+
+    /// The player left standing after the other one topped out. None while both are playing
+    /// and when both topped out on the same step.
+    pub fn winner(&self) -> Option<usize> {
+        match (self.games[0].is_over(), self.games[1].is_over()) {
+            (false, true) => Some(0),
+            (true, false) => Some(1),
+            _ => None,
+        }
+    }
+}